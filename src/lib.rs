@@ -8,7 +8,15 @@ mod tests {
     use rand::rngs::ThreadRng;
 
     use crate::sim::model::class::*;
-    use crate::sim::model::class::StreamType::{Gamma, Pareto, Poisson, Uniform};
+    use crate::sim::model::class::sim_class::SimClass;
+    use crate::sim::model::batch::{BatchDescription, BatchPolicy, BatchSize};
+    use crate::sim::model::markov::MarkovArrivalDescription;
+    use crate::sim::model::onoff::OnOffDescription;
+    use crate::sim::model::profile::IntensityProfile;
+    use crate::sim::model::system::*;
+    use crate::sim::model::topology::*;
+    use crate::sim::simulator::single_statistics::TopologyStatistics;
+    use crate::sim::model::class::StreamType::{Coxian, Deterministic, Erlang, Gamma, Hyperexponential, Lognormal, Pareto, Poisson, ShiftedExponential, TruncatedPareto, Uniform, Weibull};
 
     fn mean(data: &[f64]) -> Option<f64> {
         let sum = data.iter().sum::<f64>() as f64;
@@ -35,9 +43,38 @@ mod tests {
         }
     }
 
+    /// Blocking probability of the full-availability group with Poisson traffic (Erlang B formula)
+    fn erlang_b(v: usize, a: f64) -> f64 {
+        (1..v + 1).fold(1f64, |b, n| a * b / (n as f64 + a * b))
+    }
+
+    fn sim_classes(classes: &[Class]) -> Vec<SimClass> {
+        classes.iter().map(|tr_class| SimClass::from_class(tr_class).unwrap()).collect()
+    }
+
+    fn simulate_topology(topology: &TopologyDescription, min_state_cntr: u32) -> TopologyStatistics {
+        crate::sim::topology_simulation(topology, sim_classes(&topology.classes), min_state_cntr).unwrap()
+    }
+
+    /// Model of the group without optional components, they are set by the test
+    fn model(tr_classes: &[SimClass], v: usize) -> ModelDescription {
+        ModelDescription {
+            classes: tr_classes.iter().map(|tr_class| tr_class.tr_class.clone()).collect(),
+            v: v,
+            queue: None,
+            retrial: None,
+            preemption: None,
+            breakdown: None,
+            windows: None,
+            sleep: None,
+            servers: None,
+            service_rate: None
+        }
+    }
+
     fn test_tr_class(arrival_type: StreamType, arrival_intensity: f64, arrival_e2d2: f64) {
         let serv_intensity = 1.0;
-        let tr_class = Class::new(arrival_type, Poisson, arrival_intensity, arrival_e2d2, serv_intensity, 1.0).unwrap();
+        let tr_class = SimClass::new(arrival_type, Poisson, arrival_intensity, arrival_e2d2, serv_intensity, 1.0, 1).unwrap();
         let mut rng: ThreadRng = ThreadRng::default();
 
         let len = 10_000_000;
@@ -111,4 +148,710 @@ mod tests {
         test_tr_class(Pareto, 1.0, 3.0);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_erlang_intensity1_e2d2_3() {
+        test_tr_class(Erlang, 1.0, 3.0);
+    }
+
+    #[test]
+    fn test_erlang_intensity1_e2d2_2_5() {
+        test_tr_class(Erlang, 1.0, 2.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_erlang_intensity1_e2d2_0_5() {
+        test_tr_class(Erlang, 1.0, 0.5);
+    }
+
+    #[test]
+    fn test_hyperexponential_intensity1_e2d2_0_25() {
+        test_tr_class(Hyperexponential, 1.0, 0.25);
+    }
+
+    #[test]
+    fn test_coxian_intensity1_e2d2_0_6() {
+        test_tr_class(Coxian, 1.0, 0.6);
+    }
+
+    #[test]
+    fn test_coxian_intensity1_e2d2_1_5() {
+        test_tr_class(Coxian, 1.0, 1.5);
+    }
+
+    #[test]
+    fn test_lognormal_intensity1_e2d2_0_5() {
+        test_tr_class(Lognormal, 1.0, 0.5);
+    }
+
+    #[test]
+    fn test_weibull_intensity1_e2d2_0_5() {
+        test_tr_class(Weibull, 1.0, 0.5);
+    }
+
+    #[test]
+    fn test_weibull_intensity1_e2d2_4() {
+        test_tr_class(Weibull, 1.0, 4.0);
+    }
+
+    #[test]
+    fn test_truncated_pareto_intensity1_e2d2_1() {
+        test_tr_class(TruncatedPareto, 1.0, 1.0);
+    }
+
+    #[test]
+    fn test_truncated_pareto_shape() {
+        let mut rng: ThreadRng = ThreadRng::default();
+        for shape in [0.5, 1.8] {
+            let mut tr_class = Class::new(TruncatedPareto, Poisson, 1.0, 1.0, 1.0, 1.0, 1);
+            tr_class.set_pareto_shape(Some(shape));
+            let sim_class = SimClass::from_class(&tr_class).unwrap();
+            let samples: Vec<f64> = (0..1_000_000).map(|_| sim_class.get_time_new_call(&mut rng)).collect();
+            assert_relative_eq!(mean(&samples).unwrap(), 1.0, max_relative=0.01);
+        }
+        for shape in [1.0, 2.5] {
+            let mut tr_class = Class::new(TruncatedPareto, Poisson, 1.0, 1.0, 1.0, 1.0, 1);
+            tr_class.set_pareto_shape(Some(shape));
+            assert!(SimClass::from_class(&tr_class).is_none());
+        }
+    }
+
+    #[test]
+    fn test_pareto_without_finite_variance() {
+        use crate::sim::model::class::utils;
+
+        assert!(utils::pareto_get_scale_shape(1.0, -1.0).is_none());
+        assert!(utils::pareto_get_scale_shape(1.0, f64::INFINITY).is_none());
+        assert!(utils::pareto_get_scale_shape(1.0, 0.1).is_some());
+    }
+
+    #[test]
+    fn test_shifted_exponential_intensity1_e2d2_4() {
+        test_tr_class(ShiftedExponential, 1.0, 4.0);
+    }
+
+    #[test]
+    fn test_deterministic_intensity2() {
+        let tr_class = SimClass::new(Deterministic, Poisson, 2.0, f64::INFINITY, 1.0, 1.0, 1).unwrap();
+        let mut rng: ThreadRng = ThreadRng::default();
+        for _ in 0..1000 {
+            assert_relative_eq!(tr_class.get_time_new_call(&mut rng), 0.5);
+        }
+    }
+
+    #[test]
+    fn test_stream_e2d2_validity() {
+        assert!(Poisson.check_e2d2(1.0).is_ok());
+        assert!(Poisson.check_e2d2(2.0).is_err());
+        assert!(Uniform.check_e2d2(1.0).is_err());
+        assert!(ShiftedExponential.check_e2d2(1.5).is_ok());
+        assert!(ShiftedExponential.check_e2d2(0.5).is_err());
+        assert!(Hyperexponential.check_e2d2(2.0).is_err());
+        assert!(Deterministic.check_e2d2(f64::INFINITY).is_ok());
+        assert!(Deterministic.check_e2d2(1.0).is_err());
+        assert!(Gamma.check_e2d2(f64::INFINITY).is_err());
+        // Limits are compared with tolerance
+        assert!(Coxian.check_e2d2(2.0 + 2.0 * f64::EPSILON).is_ok());
+        assert!(Coxian.check_e2d2(2.1).is_err());
+    }
+
+    #[test]
+    fn test_infinite_e2d2_is_serialized() {
+        let tr_class = Class::new(Deterministic, Poisson, 2.0, f64::INFINITY, 1.0, 1.0, 1);
+        let json = serde_json::to_string(&tr_class).unwrap();
+        assert!(json.contains("\"inf\""));
+        let stored: Class = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.get_new_e2d2(), f64::INFINITY);
+        assert_eq!(stored.get_end_e2d2(), 1.0);
+        // Classes written before had null
+        let stored: Class = serde_json::from_str(&json.replace("\"inf\"", "null")).unwrap();
+        assert_eq!(stored.get_new_e2d2(), f64::INFINITY);
+
+        let params: StreamParams = serde_json::from_str(&serde_json::to_string(&StreamParams::new(Deterministic, 1.0, 1.0)).unwrap()).unwrap();
+        assert_eq!(params.e2d2, f64::INFINITY);
+        assert!(params.get_stream().is_some());
+    }
+
+    #[test]
+    fn test_demand_is_at_least_one_bbu() {
+        let json = serde_json::to_string(&Class::new(Poisson, Poisson, 2.0, 1.0, 1.0, 1.0, 2)).unwrap();
+        assert_eq!(serde_json::from_str::<Class>(&json).unwrap().get_t(), 2);
+        assert_eq!(serde_json::from_str::<Class>(&json.replace(",\"t\":2", "")).unwrap().get_t(), 1);
+        assert!(serde_json::from_str::<Class>(&json.replace("\"t\":2", "\"t\":0")).is_err());
+
+        let seq = "[2.0, \"Poisson\", 1.0, \"Poisson\", 1.0";
+        assert_eq!(serde_json::from_str::<SimClass>(&format!("{}]", seq)).unwrap().get_t(), 1);
+        assert!(serde_json::from_str::<SimClass>(&format!("{}, 0]", seq)).is_err());
+        let map = "{\"a\": 2.0, \"New_stream_type\": \"Poisson\", \"End_stream_type\": \"Poisson\", \"New_e2_d2\": 1.0, \"End_e2_d2\": 1.0";
+        assert_eq!(serde_json::from_str::<SimClass>(&format!("{}}}", map)).unwrap().get_t(), 1);
+        assert!(serde_json::from_str::<SimClass>(&format!("{}, \"t\": 0}}", map)).is_err());
+    }
+
+    #[test]
+    fn test_streams_at_e2d2_limits() {
+        // E²/D² on the limit of the range is valid although D/E² computed from it is rounded
+        for intensity in [0.07, 0.09, 0.13, 0.14, 1.0, 7.0] {
+            for (stream_type, e2d2) in [(Erlang, 1.0), (ShiftedExponential, 1.0), (Hyperexponential, 1.0), (Coxian, 2.0)] {
+                assert!(StreamParams::new(stream_type, intensity, e2d2).get_stream().is_some(),
+                        "{:?} with intensity {} and E²/D² {}", stream_type, intensity, e2d2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trace_replay_and_bootstrap() {
+        use crate::sim::model::trace::{TraceDescription, TraceMode};
+
+        let path = std::env::temp_dir().join(format!("any_stream_trace_{}.csv", std::process::id()));
+        std::fs::write(&path, "id,time\n0,1.0\n1,2.0\n2,3.0\n3,6.0\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let trace = TraceDescription::load(path, Some(1), TraceMode::Replay).unwrap();
+        assert_eq!(trace.no_of_samples, 4);
+        assert_relative_eq!(trace.mean, 3.0);
+        assert_relative_eq!(trace.e2d2, 9.0 / (14.0 / 3.0));
+        assert_eq!(trace, TraceDescription::load(path, Some(1), TraceMode::Replay).unwrap());
+        // Trace written with the class keeps its path, trace of the configuration file is read when it is loaded
+        let stored: TraceDescription = serde_json::from_str(&serde_json::to_string(&trace).unwrap()).unwrap();
+        assert_eq!(stored.path, path);
+        let configured: TraceDescription = serde_json::from_value(serde_json::json!({ "path": path, "column": 1 })).unwrap();
+        assert_eq!(configured, trace);
+        assert_eq!(configured.no_of_samples, 4);
+        assert_relative_eq!(configured.e2d2, trace.e2d2);
+
+        let mut rng: ThreadRng = ThreadRng::default();
+        let mut tr_class = Class::new(StreamType::Trace, Poisson, 0.5, 1.0, 1.0, 1.0, 1);
+        tr_class.set_arrival_trace(Some(trace.clone()));
+        assert_relative_eq!(tr_class.get_new_e2d2(), trace.e2d2);
+        let sim_class = SimClass::from_class(&tr_class).unwrap();
+        let mut state = sim_class::ArrivalState::default();
+        let first = sim_class.get_time_new_call_at(&mut rng, 0.0, &mut state);
+        let replayed: Vec<f64> = (0..3).map(|_| sim_class.get_time_new_call_at(&mut rng, 0.0, &mut state)).collect();
+        // Samples scaled to the mean 2 follow each other cyclically
+        let scaled = [2.0 / 3.0, 4.0 / 3.0, 2.0, 4.0];
+        let start = scaled.iter().position(|x| relative_eq!(*x, first)).unwrap();
+        for (idx, time) in replayed.iter().enumerate() {
+            assert_relative_eq!(*time, scaled[(start + idx + 1) % 4]);
+        }
+
+        let bootstrap = TraceDescription::load(path, Some(1), TraceMode::Bootstrap).unwrap();
+        tr_class.set_arrival_trace(Some(bootstrap));
+        let sim_class = SimClass::from_class(&tr_class).unwrap();
+        let len = 1_000_000;
+        let samples: Vec<f64> = (0..len).map(|_| sim_class.get_time_new_call_at(&mut rng, 0.0, &mut state)).collect();
+        assert_relative_eq!(mean(&samples).unwrap(), 2.0, max_relative=0.01);
+
+        std::fs::write(path, "1.0\n2.0\n").unwrap();
+        assert!(trace.stream(1.0).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sleep_call_takes_server_in_background_setup() {
+        use crate::sim::model::system::{SleepDescription, SleepPolicy};
+        use crate::sim::simulator::sleep::Sleep;
+
+        let description = SleepDescription { setup: StreamParams::new(Poisson, 1.0, 1.0), awake: 1, policy: SleepPolicy::Wait };
+        let mut sleep = Sleep::new(&description, 2).unwrap();
+        // The first call takes the awake server, the sleeping one starts its setup to refill the reserve
+        assert_eq!(sleep.call_add(2, 1), 0);
+        assert_eq!(sleep.take_pending_setups(), 1);
+        // The second call arrives before the setup ends and takes the server in setup
+        assert!(sleep.fits(1, 1));
+        assert_eq!(sleep.call_add(1, 1), 1);
+        assert_eq!(sleep.take_pending_setups(), 0);
+        sleep.setup_done();
+        sleep.call_end(1);
+        sleep.call_end(2);
+        // One idle server stays awake, the other one sleeps
+        assert!(sleep.fits(2, 1));
+        assert_eq!(sleep.call_add(2, 2), 1);
+    }
+
+    #[test]
+    fn test_clos_with_internal_blocking_terminates() {
+        // Three inlets share two links to middle switches, so the third inlet is never used
+        let class = Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1);
+        let clos = ClosDescription {
+            switches: 2, inlets: 3, middle: 2, link: 1,
+            connections: vec![ConnectionDescription { input: 0, output: 0, class: class.clone() },
+                              ConnectionDescription { input: 0, output: 1, class: class }],
+            selection: AlternativeSelection::Sequential
+        };
+        let result = simulate_topology(&clos.topology().unwrap(), 100);
+        // Every lost call is blocked internally, inlet and outlet always have a free BBU
+        for tr_class in &result.classes {
+            assert_relative_eq!(tr_class.external_blocking, 0.0);
+            assert_relative_eq!(tr_class.internal_blocking, tr_class.blocking);
+            assert!(tr_class.blocking > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_overflow_statistics_without_overflow_intervals() {
+        use crate::sim::simulator::single_statistics::RoutedClassStatisticsRaw;
+
+        let mut stat = RoutedClassStatisticsRaw::new(2);
+        stat.call_offered(Some(0), false, 1.0);
+        stat.call_offered(Some(1), false, 2.0);
+        let result = stat.get_class_statistics();
+        assert_eq!(result.overflow_intensity, 0.0);
+        assert_eq!(result.overflow_e2d2, 0.0);
+
+        stat.call_offered(None, true, 4.0);
+        let result = stat.get_class_statistics();
+        assert_relative_eq!(result.overflow_intensity, 0.5);
+        assert_eq!(result.overflow_e2d2, 0.0);
+    }
+
+    #[test]
+    fn test_class_statistics_without_calls() {
+        use crate::sim::simulator::single_statistics::{ClassStatisticsRaw, MacrostateRaw, RoutedClassStatisticsRaw};
+
+        let result = ClassStatisticsRaw::new(1).get_class_statistics(&vec![MacrostateRaw::new(); 2]);
+        assert_eq!(result.blocking, 0.0);
+        assert_eq!(result.preemption, 0.0);
+        assert_eq!(result.interruption, 0.0);
+        assert_eq!(result.batch_blocking, None);
+
+        let result = RoutedClassStatisticsRaw::new(2).get_class_statistics();
+        assert_eq!(result.blocking, 0.0);
+        assert_eq!(result.external_blocking, 0.0);
+        assert_eq!(result.internal_blocking, 0.0);
+        assert_eq!(result.carried, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_processor_sharing_sojourn_per_call() {
+        // Calls demand 2 BBUs, capacity is shared by calls, so it is M/M/1/4-PS with ρ = 0.5 and mean sojourn 26/15
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 0.5, 1.0, 1.0, 1.0, 2).unwrap()];
+        let mut model = model(&tr_classes, 8);
+        model.service_rate = Some(ServiceRateDescription::ProcessorSharing { capacity: 1.0 });
+        let result = crate::sim::simulation(&model, tr_classes, 20_000).unwrap();
+        let sharing = result.sharing.unwrap();
+        assert_relative_eq!(sharing.mean_sojourn, 26.0 / 15.0, max_relative=0.03);
+        // Calls never start their service in odd macrostates
+        assert_eq!(sharing.sojourn[1], 0.0);
+    }
+
+    #[test]
+    fn test_sojourn_includes_waiting_in_queue() {
+        // Single server with 3 waiting places is M/M/1/4 with ρ = 0.5 and mean sojourn 26/15
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 0.5, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 1);
+        model.queue = Some(QueueDescription { capacity: Some(3), patience: None });
+        model.service_rate = Some(ServiceRateDescription::StateDependent(vec![1.0]));
+        let result = crate::sim::simulation(&model, tr_classes, 20_000).unwrap();
+        assert_relative_eq!(result.sharing.unwrap().mean_sojourn, 26.0 / 15.0, max_relative=0.03);
+    }
+
+    #[test]
+    fn test_windows_without_period_are_merged() {
+        use crate::sim::simulator::single_statistics::EventType;
+        use crate::sim::simulator::windows::Windows;
+
+        let mut windows = Windows::new(&WindowsDescription { length: 1.0, period: None }).unwrap();
+        windows.statistics_init(2, 1);
+        for _ in 0..5000 {
+            windows.update(EventType::Other, 0, 1, 1.0);
+        }
+        let result = windows.statistics_preview().unwrap();
+        // 5000 windows of length 1 don't fit into 1024 windows, so they are merged into windows of length 8
+        assert_eq!(result.len(), 625);
+        assert!(result.iter().all(|window| window.length == 8.0 && window.states[1] == 1.0));
+        // There were no arrivals, so there is no blocking either
+        assert!(result.iter().all(|window| window.blocking == vec![0.0]));
+        assert_eq!(result[624].start, 4992.0);
+    }
+
+    #[test]
+    fn test_multi_rate_call_occupies_t_bbus() {
+        // Calls demanding 2 BBUs in 4 BBUs see Erlang B for 2 servers, P = [0.4, 0, 0.4, 0, 0.2]
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 2).unwrap()];
+        let model = model(&tr_classes, 4);
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.states[0].p, 0.4, max_relative=0.05);
+        assert_relative_eq!(result.states[2].p, 0.4, max_relative=0.05);
+        assert_relative_eq!(result.states[4].p, 0.2, max_relative=0.05);
+        assert_eq!(result.states[1].p, 0.0);
+        assert_eq!(result.states[3].p, 0.0);
+    }
+
+    #[test]
+    fn test_multi_rate_classes_kaufman_roberts() {
+        // Kaufman-Roberts recursion for V = 3, a1 = 1, t1 = 1, a2 = 0.5, t2 = 2 gives P = [3, 3, 3, 2] / 11
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap(),
+                              SimClass::new(Poisson, Poisson, 0.5, 1.0, 1.0, 1.0, 2).unwrap()];
+        let model = model(&tr_classes, 3);
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, 2.0 / 11.0, max_relative=0.05);
+        assert_relative_eq!(result.classes[1].blocking, 5.0 / 11.0, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_queue_erlang_c() {
+        // M/M/2 with a = 1, call waits with probability given by Erlang C formula, 1/3
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 2);
+        model.queue = Some(QueueDescription { capacity: None, patience: None });
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        let queue = result.queue.unwrap();
+        assert_relative_eq!(queue.p_wait, 1.0 / 3.0, max_relative=0.05);
+        assert_relative_eq!(queue.waiting_time_mean, 1.0 / 3.0, max_relative=0.1);
+        assert_eq!(result.classes[0].blocking, 0.0);
+    }
+
+    #[test]
+    fn test_queue_abandoned_calls_are_lost() {
+        // M/M/1 with a = 1 and patience rate equal to service rate: number of calls in the system
+        // is Poisson distributed, call is served with probability (1 - e^(-a)) / a, so it is lost with e^(-1)
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 1);
+        model.queue = Some(QueueDescription { capacity: None, patience: Some(StreamParams::new(Poisson, 1.0, 1.0)) });
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, (-1f64).exp(), max_relative=0.05);
+        assert_relative_eq!(result.queue.unwrap().p_abandon, (-1f64).exp(), max_relative=0.05);
+    }
+
+    #[test]
+    fn test_queue_statistics_without_calls() {
+        use crate::sim::simulator::single_statistics::QueueStatisticsRaw;
+
+        let result = QueueStatisticsRaw::new().get_queue_statistics();
+        assert_eq!(result.waiting_time_mean, 0.0);
+        assert_eq!(result.waiting_time_variance, 0.0);
+        assert_eq!(result.p_wait, 0.0);
+        assert_eq!(result.p_abandon, 0.0);
+        assert_eq!(result.p_rejected, 0.0);
+    }
+
+    #[test]
+    fn test_overloaded_infinite_queue_is_reported() {
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 2.0, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 2);
+        model.queue = Some(QueueDescription { capacity: None, patience: None });
+        assert!(model.check().is_err());
+        model.queue = Some(QueueDescription { capacity: Some(10), patience: None });
+        assert!(model.check().is_ok());
+        model.queue = Some(QueueDescription { capacity: None, patience: Some(StreamParams::new(Poisson, 1.0, 1.0)) });
+        assert!(model.check().is_ok());
+    }
+
+    #[test]
+    fn test_retrial_orbit_single_server_utilisation() {
+        // Every blocked call retries till it is served, so the server is busy with probability ρ
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 0.5, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 1);
+        model.retrial = Some(RetrialDescription { delay: StreamParams::new(Poisson, 1.0, 1.0), persistence: 1.0 });
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.states[1].p, 0.5, max_relative=0.05);
+        assert_eq!(result.orbit.unwrap().p_abandon, 0.0);
+    }
+
+    #[test]
+    fn test_orbit_statistics_without_calls() {
+        use crate::sim::simulator::single_statistics::OrbitStatisticsRaw;
+
+        let result = OrbitStatisticsRaw::new().get_orbit_statistics();
+        assert_eq!(result.retrials_per_successful_call, 0.0);
+        assert_eq!(result.primary_congestion, 0.0);
+        assert_eq!(result.repeated_congestion, 0.0);
+        assert_eq!(result.p_abandon, 0.0);
+    }
+
+    #[test]
+    fn test_finite_sources_engset() {
+        // Engset call congestion for N = 3 sources, V = 2 and β = 0.5: β²/(1 + 2β + β²) = 1/9
+        let mut tr_class = SimClass::new(Poisson, Poisson, 0.5, 1.0, 1.0, 1.0, 1).unwrap();
+        tr_class.tr_class.set_sources(Some(3));
+        let tr_classes = vec![tr_class];
+        let model = model(&tr_classes, 2);
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, 1.0 / 9.0, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_preemption_high_priority_class_sees_erlang_b() {
+        // Calls of the higher priority class preempt the other ones, so they see the group as if they were alone
+        let mut high = SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap();
+        high.tr_class.set_priority(1);
+        let tr_classes = vec![high, SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 2);
+        model.preemption = Some(PreemptionPolicy::Drop);
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, erlang_b(2, 1.0), max_relative=0.05);
+        assert_eq!(result.classes[0].preemption, 0.0);
+        assert!(result.classes[1].preemption > 0.0);
+    }
+
+    #[test]
+    fn test_class_intensities() {
+        // Offered traffic is a = λ/μ, so λ = 2 and μ = 0.5 give a = 4
+        let tr_class = Class::new(Poisson, Poisson, 2.0, 1.0, 0.5, 1.0, 1);
+        assert_relative_eq!(tr_class.get_a(), 4.0);
+        assert_relative_eq!(tr_class.get_new_intensity(), 2.0);
+        assert_relative_eq!(tr_class.get_end_intensity(), 0.5);
+    }
+
+    #[test]
+    fn test_overflow_from_primary_group() {
+        // Primary group with one BBU and a = 1 carries 1 - E(1, 1) = 0.5 of calls, the rest overflows
+        let overflow = OverflowDescription {
+            primary: vec![PrimaryGroupDescription { v: 1, classes: vec![Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1)] }],
+            secondary: 2
+        };
+        let result = simulate_topology(&overflow.topology().unwrap(), 50_000);
+        assert_relative_eq!(result.classes[0].carried[0], 0.5, max_relative=0.05);
+        assert_relative_eq!(result.classes[0].overflow_intensity, 0.5, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_limited_availability_of_single_bbu_subgroups() {
+        // Single BBU calls fit into any free BBU, so subgroups work as the full-availability group
+        let limited = LimitedAvailabilityDescription {
+            subgroups: vec![1, 1],
+            classes: vec![Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1)],
+            selection: AlternativeSelection::Sequential
+        };
+        let result = simulate_topology(&limited.topology().unwrap(), 50_000);
+        assert_relative_eq!(result.classes[0].blocking, erlang_b(2, 1.0), max_relative=0.05);
+    }
+
+    #[test]
+    fn test_network_route_over_two_links() {
+        // The only route occupies both links at the same time, so it is blocked like the single link
+        let network = NetworkDescription {
+            links: vec![LinkDescription { name: "A".to_string(), v: 2 }, LinkDescription { name: "B".to_string(), v: 2 }],
+            routes: vec![RouteDescription { name: "AB".to_string(), links: vec!["A".to_string(), "B".to_string()],
+                                            class: Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1) }]
+        };
+        let result = simulate_topology(&network.topology().unwrap(), 50_000);
+        assert_relative_eq!(result.classes[0].blocking, erlang_b(2, 1.0), max_relative=0.05);
+    }
+
+    #[test]
+    fn test_too_many_joint_states_are_reported() {
+        // Route over both groups and the route over the first group reach about 500 000 joint states
+        let class = Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1);
+        let topology = TopologyDescription {
+            groups: vec![1000, 1000],
+            classes: vec![class.clone(), class],
+            alternatives: vec![vec![vec![0, 1]], vec![vec![0]]],
+            selection: AlternativeSelection::default(),
+            handoff: None,
+            demands: None
+        };
+        assert!(crate::sim::simulator::Simulator::with_topology(&sim_classes(&topology.classes), &topology).is_err());
+    }
+
+    #[test]
+    fn test_breakdown_of_single_server() {
+        // Server fails with rate 0.1 and is repaired with rate 1, so it works with probability 10/11.
+        // Call is admitted only to the working idle server, this happens with probability 10/21.
+        let tr_classes = sim_classes(&[Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1)]);
+        let mut model = model(&tr_classes, 1);
+        model.breakdown = Some(BreakdownDescription {
+            failure: StreamParams::new(Poisson, 0.1, 1.0),
+            repair: StreamParams::new(Poisson, 1.0, 1.0),
+            policy: FailurePolicy::Lost
+        });
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.availability.unwrap().failed_distribution[0], 10.0 / 11.0, max_relative=0.05);
+        assert_relative_eq!(result.classes[0].blocking, 11.0 / 21.0, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_failed_server_is_chosen_among_all_servers() {
+        // Call occupies 2 of 3 servers, so it is interrupted by 2/3 of failures, the other ones hit the idle server
+        let description = BreakdownDescription {
+            failure: StreamParams::new(Poisson, 0.1, 1.0),
+            repair: StreamParams::new(Poisson, 1.0, 1.0),
+            policy: FailurePolicy::Lost
+        };
+        let mut rng = rand::thread_rng();
+        let mut interrupted = 0;
+        for _ in 0..30_000 {
+            let mut breakdown = crate::sim::simulator::breakdown::Breakdown::new(&description, 3).unwrap();
+            breakdown.bind(7, 2);
+            if let Some(call_id) = breakdown.server_fail(&mut rng) {
+                assert_eq!(call_id, 7);
+                interrupted += 1;
+            }
+        }
+        assert_relative_eq!(interrupted as f64 / 30_000.0, 2.0 / 3.0, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_elastic_calls_compress() {
+        // Two calls compressed to one BBU are served with half rate, so the group works as M/M/1/2 with ρ = 1
+        let mut tr_class = Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 2);
+        tr_class.set_t_min(Some(1));
+        let tr_classes = sim_classes(&[tr_class]);
+        let model = model(&tr_classes, 2);
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, 1.0 / 3.0, max_relative=0.05);
+        assert_relative_eq!(result.compression.unwrap().compression[4], 0.5);
+    }
+
+    #[test]
+    fn test_intensity_profile_in_windows() {
+        // Holding time is short compared with steps of the profile, so every window sees Erlang B of its own intensity
+        let mut tr_class = Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1);
+        tr_class.set_profile(Some(IntensityProfile::PiecewiseConstant { steps: vec![(0.0, 1.5), (500.0, 0.5)], period: Some(1000.0) }));
+        let tr_classes = sim_classes(&[tr_class]);
+        let mut model = model(&tr_classes, 2);
+        model.windows = Some(WindowsDescription { length: 500.0, period: Some(1000.0) });
+        let result = crate::sim::simulation(&model, tr_classes, 200_000).unwrap();
+        let windows = result.windows.unwrap();
+        assert_relative_eq!(windows[0].blocking[0], erlang_b(2, 1.5), max_relative=0.05);
+        assert_relative_eq!(windows[1].blocking[0], erlang_b(2, 0.5), max_relative=0.1);
+    }
+
+    #[test]
+    fn test_batch_all_or_nothing() {
+        // Pairs of calls arrive with intensity 1 to 2 BBUs, P = [0.4, 0.4, 0.2], the pair fits only into the empty group
+        let mut tr_class = Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1);
+        tr_class.set_batch(Some(BatchDescription { size: BatchSize::Fixed(2), policy: BatchPolicy::AllOrNothing }));
+        let tr_classes = sim_classes(&[tr_class]);
+        let model = model(&tr_classes, 2);
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.states[0].p, 0.4, max_relative=0.05);
+        assert_relative_eq!(result.classes[0].blocking, 0.6, max_relative=0.05);
+        assert_relative_eq!(result.classes[0].batch_blocking.unwrap(), 0.6, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_mmpp_is_scaled_to_class_intensity() {
+        let mut tr_class = Class::new(Poisson, Poisson, 2.0, 1.0, 1.0, 1.0, 1);
+        tr_class.set_arrival_map(Some(MarkovArrivalDescription::Mmpp {
+            generator: vec![vec![-0.1, 0.1], vec![0.1, -0.1]],
+            rates: vec![0.1, 10.0]
+        }));
+        let sim_class = SimClass::from_class(&tr_class).unwrap();
+        let mut rng: ThreadRng = ThreadRng::default();
+        let mut state = sim_class::ArrivalState::default();
+        let samples: Vec<f64> = (0..1_000_000).map(|_| sim_class.get_time_new_call_at(&mut rng, 0.0, &mut state)).collect();
+        assert_relative_eq!(mean(&samples).unwrap(), 0.5, max_relative=0.05);
+        // Arrivals are more bursty than Poisson ones
+        let e2d2 = (mean(&samples).unwrap() / std_deviation(&samples).unwrap()).powi(2);
+        assert!(e2d2 < 0.5);
+    }
+
+    #[test]
+    fn test_on_off_sources_are_scaled_to_class_intensity() {
+        let mut tr_class = Class::new(Poisson, Poisson, 2.0, 1.0, 1.0, 1.0, 1);
+        tr_class.set_arrival_on_off(Some(OnOffDescription { sources: 10, hurst: 0.55, mean_on: 1.0, mean_off: 3.0 }));
+        let sim_class = SimClass::from_class(&tr_class).unwrap();
+        let mut rng: ThreadRng = ThreadRng::default();
+        let mut state = sim_class::ArrivalState::default();
+        let samples: Vec<f64> = (0..1_000_000).map(|_| sim_class.get_time_new_call_at(&mut rng, 0.0, &mut state)).collect();
+        assert_relative_eq!(mean(&samples).unwrap(), 0.5, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_cells_with_rare_handoffs() {
+        // Calls almost never leave their cell, so every cell is the full-availability group
+        let cell = |neighbour: usize| CellDescription { v: 2, guard: 0, neighbours: vec![neighbour],
+                                                        classes: vec![Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1)] };
+        let cellular = CellularDescription { cells: vec![cell(1), cell(0)], dwell: StreamParams::new(Poisson, 0.001, 1.0) };
+        let result = simulate_topology(&cellular.topology().unwrap(), 50_000);
+        for cell in result.cells.unwrap() {
+            assert_relative_eq!(cell.new_call_blocking, erlang_b(2, 1.0), max_relative=0.05);
+        }
+    }
+
+    #[test]
+    fn test_cell_statistics_without_calls() {
+        use crate::sim::simulator::single_statistics::CellStatisticsRaw;
+
+        let mut stat = CellStatisticsRaw::new();
+        let result = stat.get_cell_statistics();
+        assert_eq!(result.new_call_blocking, 0.0);
+        assert_eq!(result.handoff_dropping, 0.0);
+
+        stat.new_call(true);
+        let result = stat.get_cell_statistics();
+        assert_eq!(result.new_call_blocking, 1.0);
+        assert_eq!(result.handoff_dropping, 0.0);
+    }
+
+    #[test]
+    fn test_sleeping_servers_with_short_setup() {
+        // Setup is much shorter than the holding time, so sleeping servers don't change Erlang B blocking
+        let tr_classes = sim_classes(&[Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1)]);
+        let mut model = model(&tr_classes, 2);
+        model.sleep = Some(SleepDescription { setup: StreamParams::new(Poisson, 1000.0, 1.0), awake: 0, policy: SleepPolicy::Wait });
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, erlang_b(2, 1.0), max_relative=0.05);
+        // Servers are awake only when they serve calls, the mean number of calls is a·(1 - E)
+        assert_relative_eq!(result.energy.unwrap().mean_awake, 1.0 - erlang_b(2, 1.0), max_relative=0.05);
+    }
+
+    #[test]
+    fn test_clos_strict_sense_nonblocking() {
+        // m = 2n - 1 middle switches, connection always finds a free path (Clos theorem)
+        let class = Class::new(Poisson, Poisson, 0.5, 1.0, 1.0, 1.0, 1);
+        let clos = ClosDescription {
+            switches: 2, inlets: 2, middle: 3, link: 1,
+            connections: (0..4).map(|idx| ConnectionDescription { input: idx / 2, output: idx % 2, class: class.clone() }).collect(),
+            selection: AlternativeSelection::Random
+        };
+        let result = simulate_topology(&clos.topology().unwrap(), 1_000);
+        for tr_class in &result.classes {
+            assert_eq!(tr_class.internal_blocking, 0.0);
+            assert!(tr_class.external_blocking > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_fast_servers() {
+        // Servers twice faster than the nominal rate halve the offered traffic
+        let tr_classes = sim_classes(&[Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1)]);
+        let mut model = model(&tr_classes, 2);
+        model.servers = Some(ServersDescription { rates: vec![2.0, 2.0], hunting: HuntingPolicy::FastestFree });
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, erlang_b(2, 0.5), max_relative=0.05);
+        // Servers carry the traffic a·(1 - E) together
+        assert_relative_eq!(result.servers.unwrap().carried.iter().sum::<f64>(), 0.5 * (1.0 - erlang_b(2, 0.5)), max_relative=0.05);
+    }
+
+    #[test]
+    fn test_resource_pool_with_proportional_demands() {
+        // Demands are proportional to capacities, so both resources are full at the same time
+        let pool = ResourcePoolDescription {
+            resources: vec![ResourceDescription { name: "cpu".to_string(), v: 2 }, ResourceDescription { name: "ram".to_string(), v: 4 }],
+            classes: vec![ResourceClassDescription { demands: vec![1, 2], class: Class::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1) }]
+        };
+        let result = simulate_topology(&pool.topology().unwrap(), 50_000);
+        assert_relative_eq!(result.classes[0].blocking, erlang_b(2, 1.0), max_relative=0.05);
+    }
+
+    #[test]
+    fn test_state_dependent_service_rate() {
+        // Calls are served twice faster when both BBUs are busy, death rates are 1 and 4, so P = [4, 4, 1] / 9
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut model = model(&tr_classes, 2);
+        model.service_rate = Some(ServiceRateDescription::StateDependent(vec![1.0, 2.0]));
+        let result = crate::sim::simulation(&model, tr_classes, 50_000).unwrap();
+        assert_relative_eq!(result.classes[0].blocking, 1.0 / 9.0, max_relative=0.05);
+    }
+
+    #[test]
+    fn test_conflicting_components_are_reported() {
+        // Elastic calls can't wait in the queue
+        let mut tr_class = SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 2).unwrap();
+        tr_class.tr_class.set_t_min(Some(1));
+        let tr_classes = vec![tr_class];
+        let mut elastic = model(&tr_classes, 2);
+        elastic.queue = Some(QueueDescription { capacity: None, patience: None });
+        assert!(elastic.check().is_err());
+        assert!(crate::sim::simulation(&elastic, tr_classes, 100).is_err());
+
+        // Every server needs its service rate
+        let tr_classes = vec![SimClass::new(Poisson, Poisson, 1.0, 1.0, 1.0, 1.0, 1).unwrap()];
+        let mut servers = model(&tr_classes, 2);
+        servers.servers = Some(ServersDescription { rates: vec![1.0], hunting: HuntingPolicy::Sequential });
+        assert!(servers.check().is_ok());
+        assert!(crate::sim::simulation(&servers, tr_classes, 100).is_err());
+    }
+}
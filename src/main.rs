@@ -23,10 +23,17 @@ use clap::ValueHint::CommandString;
 use mongodb::options::{AuthMechanism, CredentialBuilder};
 use mongodb::sync;
 
-use crate::sim::model::class::{Class, StreamType, sim_class::SimClass};
-use crate::sim::model::system::ModelDescription;
+use crate::sim::model::class::{Class, StreamType, StreamParams, sim_class::SimClass};
+use crate::sim::model::batch::{BatchDescription, BatchPolicy, BatchSize};
+use crate::sim::model::markov::MarkovArrivalDescription;
+use crate::sim::model::onoff::OnOffDescription;
+use crate::sim::model::profile::IntensityProfile;
+use crate::sim::model::system::{BreakdownDescription, FailurePolicy, ModelDescription, PreemptionPolicy, QueueDescription, RetrialDescription, SleepDescription, SleepPolicy, ServersDescription, HuntingPolicy, ServiceRateDescription, WindowsDescription};
+use crate::sim::model::topology::{TopologyConfig, TopologyDescription};
+use crate::sim::model::trace::{TraceDescription, TraceMode};
+use crate::sim::simulator::Simulator;
 use crate::sim::simulator::simulations_statistics::{SimStatisticsMultiV, StatisticsMultiSimulations};
-use crate::sim::simulator::single_statistics::{Macrostate, StatisticsFinalized, StatisticsRunExperiment};
+use crate::sim::simulator::single_statistics::{Macrostate, StatisticsFinalized, StatisticsRunExperiment, TopologyStatistics};
 
 mod sim;
 
@@ -58,11 +65,11 @@ struct SimulateArgs {
     #[clap(long, default_value_t=1.0)]
     a_delta: f64,
 
-    /// Arrival stream type
+    /// Arrival stream type. Trace and deterministic streams (E²/D² = inf) use only the first E²/D² of the grid
     #[clap(long, default_value="uniform")]
     call_stream: Vec<String>,
 
-    /// Service stream type
+    /// Service stream type. Trace and deterministic streams (E²/D² = inf) use only the first E²/D² of the grid
     #[clap(long, default_value="poisson")]
     serv_stream: Vec<String>,
 
@@ -90,6 +97,229 @@ struct SimulateArgs {
     #[clap(long, default_value_t=1.0)]
     ss_e2_d2_delta: f64,
 
+    /// Number of basic bandwidth units (BBU) demanded by a single call
+    #[clap(long, default_value="1")]
+    demand: Vec<usize>,
+
+    /// Number of traffic sources of the swept class. Infinite number of sources if not given.
+    /// Then offered traffic and arrival stream describe single idle source
+    #[clap(long)]
+    sources: Option<usize>,
+
+    /// Shape of truncated Pareto streams of the swept class, in range 0..2 except 1. It is 1.5 if not given
+    #[clap(long)]
+    pareto_shape: Option<f64>,
+
+    /// Minimum number of BBUs of the swept class. Its calls are elastic and compress down to this
+    /// demand when the group is congested. Calls don't compress if not given
+    #[clap(long)]
+    min_demand: Option<usize>,
+
+    /// Two-phase MMPP arrival stream of the swept class: rate1,rate2,switch12,switch21.
+    /// The process is scaled to the offered traffic, so only ratios of its intensities matter.
+    /// Replaces the arrival stream type
+    #[clap(long)]
+    mmpp: Option<String>,
+
+    /// JSON file with Markovian arrival process of the swept class, e.g.
+    /// {"Mmpp": {"generator": [[-0.1, 0.1], [0.2, -0.2]], "rates": [5.0, 0.5]}} or
+    /// {"Map": {"d0": [[...], ...], "d1": [[...], ...]}}. It is scaled to the offered traffic
+    #[clap(long)]
+    arrival_map_file: Option<std::path::PathBuf>,
+
+    /// Number of aggregated ON/OFF sources of the swept class. Sources with Pareto ON and OFF periods
+    /// generate long-range dependent stream with offered traffic of the class. Replaces the arrival stream type
+    #[clap(long)]
+    on_off_sources: Option<usize>,
+
+    /// Target Hurst parameter of ON/OFF sources, in range 0.5..1
+    #[clap(long, default_value_t=0.8)]
+    hurst: f64,
+
+    /// Mean ON period of single source
+    #[clap(long, default_value_t=1.0)]
+    mean_on: f64,
+
+    /// Mean OFF period of single source
+    #[clap(long, default_value_t=1.0)]
+    mean_off: f64,
+
+    /// File with recorded inter-arrival times of the swept class, used by the trace arrival stream.
+    /// Times are scaled to the offered traffic, E²/D² is taken from the trace
+    #[clap(long)]
+    arrival_trace: Option<String>,
+
+    /// Column of CSV file with inter-arrival times (counted from 0), the file has one time per line if not given
+    #[clap(long)]
+    arrival_trace_column: Option<usize>,
+
+    /// File with recorded holding times of the swept class, used by the trace service stream.
+    /// Times are scaled to the mean service time 1, E²/D² is taken from the trace
+    #[clap(long)]
+    service_trace: Option<String>,
+
+    /// Column of CSV file with holding times (counted from 0), the file has one time per line if not given
+    #[clap(long)]
+    service_trace_column: Option<usize>,
+
+    /// How times are drawn from traces: replay (sequentially, keeps correlation) or bootstrap (resampling)
+    #[clap(long, default_value="replay")]
+    trace_mode: String,
+
+    /// Batch size distribution of the swept class: fixed:size, geometric:mean, poisson:mean (shifted by 1)
+    /// or empirical:p1,p2,... (probabilities of sizes 1, 2, ...). Calls arrive one by one if not given.
+    /// Offered traffic describes arrivals of batches
+    #[clap(long)]
+    batch_size: Option<String>,
+
+    /// Admission of the batch that doesn't fit into the group: all (all-or-nothing) or partial
+    #[clap(long, default_value="partial")]
+    batch_policy: String,
+
+    /// CSV file with the arrival intensity profile of the swept class. Every row is "start_time,multiplier",
+    /// the first step starts at 0. Arrival intensity is stationary if no profile is given
+    #[clap(long)]
+    profile_csv: Option<std::path::PathBuf>,
+
+    /// Period after which the profile from CSV file repeats. The last step lasts forever if not given
+    #[clap(long)]
+    profile_period: Option<f64>,
+
+    /// Period of sinusoidal arrival intensity profile of the swept class (e.g. the day)
+    #[clap(long)]
+    profile_sinusoid_period: Option<f64>,
+
+    /// Amplitude of sinusoidal profile relative to the mean intensity, in range 0..1
+    #[clap(long, default_value_t=0.5)]
+    profile_sinusoid_amplitude: f64,
+
+    /// Phase of sinusoidal profile in radians
+    #[clap(long, default_value_t=0.0)]
+    profile_sinusoid_phase: f64,
+
+    /// Priority of the swept class. Calls of higher priority can preempt calls of lower priority
+    #[clap(long, default_value_t=0)]
+    priority: u32,
+
+    /// Preemption policy: resume or drop. Priorities are ignored if not given
+    #[clap(long)]
+    preemption: Option<String>,
+
+    /// Enables waiting room in front of the group
+    #[clap(long)]
+    queue: bool,
+
+    /// Number of waiting places. Queue is infinite if not given
+    #[clap(long)]
+    queue_capacity: Option<usize>,
+
+    /// Patience stream type of waiting calls. Calls never abandon the queue if not given
+    #[clap(long)]
+    patience_stream: Option<String>,
+
+    /// Patience stream intensity (1 / mean patience time)
+    #[clap(long, default_value_t=1.0)]
+    patience_intensity: f64,
+
+    /// Patience stream parameters. ExpectedValue²/Variance²
+    #[clap(long, default_value_t=1.0)]
+    patience_e2_d2: f64,
+
+    /// Retrial delay stream type. Blocked calls are lost if not given
+    #[clap(long)]
+    retrial_stream: Option<String>,
+
+    /// Retrial delay stream intensity (1 / mean time between attempts)
+    #[clap(long, default_value_t=1.0)]
+    retrial_intensity: f64,
+
+    /// Retrial delay stream parameters. ExpectedValue²/Variance²
+    #[clap(long, default_value_t=1.0)]
+    retrial_e2_d2: f64,
+
+    /// Probability that blocked call retries instead of leaving the system
+    #[clap(long, default_value_t=1.0)]
+    persistence: f64,
+
+    /// Failure stream type of single server (BBU). Servers never fail if not given
+    #[clap(long)]
+    failure_stream: Option<String>,
+
+    /// Failure stream intensity (1 / mean time between repair and the next failure)
+    #[clap(long, default_value_t=0.01)]
+    failure_intensity: f64,
+
+    /// Failure stream parameters. ExpectedValue²/Variance²
+    #[clap(long, default_value_t=1.0)]
+    failure_e2_d2: f64,
+
+    /// Repair stream type
+    #[clap(long, default_value="poisson")]
+    repair_stream: String,
+
+    /// Repair stream intensity (1 / mean repair time)
+    #[clap(long, default_value_t=1.0)]
+    repair_intensity: f64,
+
+    /// Repair stream parameters. ExpectedValue²/Variance²
+    #[clap(long, default_value_t=1.0)]
+    repair_e2_d2: f64,
+
+    /// What happens to the call of the failing server: lost or move (to the free server if one exists)
+    #[clap(long, default_value="lost")]
+    failure_policy: String,
+
+    /// Setup stream type of sleeping server (BBU). Idle servers never sleep if not given
+    #[clap(long)]
+    setup_stream: Option<String>,
+
+    /// Setup stream intensity (1 / mean setup time)
+    #[clap(long, default_value_t=1.0)]
+    setup_intensity: f64,
+
+    /// Setup stream parameters. ExpectedValue²/Variance²
+    #[clap(long, default_value_t=1.0)]
+    setup_e2_d2: f64,
+
+    /// Number of idle servers that stay awake
+    #[clap(long, default_value_t=0)]
+    awake_servers: usize,
+
+    /// What happens to the call that needs sleeping servers: wait (for their setup) or block
+    #[clap(long, default_value="wait")]
+    sleep_policy: String,
+
+    /// Service rate multipliers of servers, e.g. 2.0,1.0. The list is repeated for groups with more servers.
+    /// All servers are identical if not given
+    #[clap(long)]
+    server_rates: Option<String>,
+
+    /// How the free server is chosen: sequential, random, fastest or longest_idle
+    #[clap(long, default_value="sequential")]
+    hunting: String,
+
+    /// Service rate of calls depending on the macrostate: ps:capacity (processor sharing, capacity is shared
+    /// by calls in service) or rates:r1,r2,... (rate of every call with 1, 2, ... occupied BBUs). Calls are served with unit rate if not given
+    #[clap(long)]
+    service_rate: Option<String>,
+
+    /// Length of the time window of statistics. Statistics are collected only for the whole simulation if not given
+    #[clap(long)]
+    window_length: Option<f64>,
+
+    /// Period after which the windows repeat, e.g. the period of intensity profile.
+    /// Windows follow each other from the start of simulation if not given, then at most 1024 windows are kept
+    /// and neighbouring windows are merged into twice longer ones when the simulation exceeds them
+    #[clap(long)]
+    window_period: Option<f64>,
+
+    /// Additional traffic class offered together with the swept one.
+    /// Format: arrival_stream:arrival_e2d2:service_stream:service_e2d2:a:t[:sources[:priority[:t_min]]], e.g. poisson:1:poisson:1:2.0:2
+    /// Empty sources field means infinite number of sources, e.g. poisson:1:poisson:1:2.0:2::1.
+    /// Calls of the class with t_min are elastic, e.g. poisson:1:poisson:1:2.0:4::0:2
+    #[clap(long)]
+    background_class: Vec<String>,
+
     /// Minimum no of occurrence of every state to finish simulation experiment
     #[clap(short, default_value_t=100)]
     mim_state_cntr: u32,
@@ -108,6 +338,37 @@ struct SimulateArgs {
     output_path: std::path::PathBuf,
 }
 
+#[derive(Parser)]
+struct SimulateTopologyArgs {
+    /// JSON file with the description of groups and traffic classes, e.g.
+    /// {"Overflow": {"primary": [{"v": 5, "classes": [...]}, ...], "secondary": 10}} or
+    /// {"LimitedAvailability": {"subgroups": [4, 4, 2], "classes": [...], "selection": "LeastLoaded"}} or
+    /// {"Network": {"links": [{"name": "AB", "v": 10}, ...], "routes": [{"name": "A-C", "links": ["AB", "BC"], "class": {...}}, ...]}}.
+    /// {"Cellular": {"cells": [{"v": 10, "guard": 2, "neighbours": [1], "classes": [...]}, ...], "dwell": {"stream_type": "Poisson", "intensity": 1.0, "e2d2": 1.0}}} or
+    /// {"Clos": {"switches": 4, "inlets": 4, "middle": 4, "link": 1, "connections": [{"input": 0, "output": 1, "class": {...}}, ...], "selection": "MostLoaded"}} or
+    /// {"ResourcePool": {"resources": [{"name": "CPU", "v": 32}, {"name": "RAM", "v": 64}], "classes": [{"demands": [2, 4], "class": {...}}, ...]}}.
+    /// Subgroup selection (path hunting): Sequential, Random, LeastLoaded or MostLoaded.
+    /// Results of groups (links) and classes (routes) are in the order of the configuration file
+    #[clap(short, long)]
+    config: std::path::PathBuf,
+
+    /// Minimum no of occurrence of every state in every group to finish simulation experiment
+    #[clap(short, default_value_t=100)]
+    mim_state_cntr: u32,
+
+    /// Number of series in simulation experiment
+    #[clap(short, default_value_t=3)]
+    no_of_series: usize,
+
+    /// Number of threads
+    #[clap(short, default_value_t=8)]
+    threads_no: u32,
+
+    /// Save results of every series to JSON file
+    #[clap(short, long, default_value="results.json")]
+    output_path: std::path::PathBuf,
+}
+
 #[derive(Parser)]
 struct ConfigureMongoArgss {
     /// Mongo URI
@@ -138,6 +399,7 @@ struct ConfigureMongoArgss {
 #[derive(Subcommand)]
 enum Commands {
     Simulate (SimulateArgs),
+    SimulateTopology (SimulateTopologyArgs),
     ConfigureMongo (ConfigureMongoArgss)
 }
 
@@ -184,8 +446,8 @@ impl ::std::default::Default for MyConfig {
 }
 
 struct SimulationTask {
-    tr_class: SimClass,
-    v : usize,
+    tr_classes: Vec<SimClass>,
+    model: ModelDescription,
     mim_state_cntr: u32,
     sim_no: u32
 }
@@ -239,34 +501,35 @@ fn read_finilized_statistics(model: &ModelDescription, db: &mongodb::sync::Datab
         .collect()
 }
 
-fn generate_ML_results(results: BTreeMultiMap<ModelDescription, StatisticsFinalized>) -> BTreeMap<Class, SimStatisticsMultiV> {
-    let mut final_results: BTreeMap<Class, SimStatisticsMultiV> = BTreeMap::new();
+fn generate_ML_results(results: BTreeMultiMap<ModelDescription, StatisticsFinalized>) -> BTreeMap<Vec<Class>, SimStatisticsMultiV> {
+    let mut final_results: BTreeMap<Vec<Class>, SimStatisticsMultiV> = BTreeMap::new();
 
     for (key, values) in results {
-        let mut map_item = final_results.get_mut(&key.class);
+        let mut map_item = final_results.get_mut(&key.classes);
         match  map_item {
             Some(itm) => {
                 let stat_signel_v = StatisticsMultiSimulations::statistics_proc(&values.into_iter().collect(), key.v);
                 itm.results.push_back(stat_signel_v);
             },
             None => {
-                let mut aggregated_result:SimStatisticsMultiV = SimStatisticsMultiV::new(key.class);
+                let mut aggregated_result:SimStatisticsMultiV = SimStatisticsMultiV::new(key.classes.clone());
                 let stat_signel_v = StatisticsMultiSimulations::statistics_proc(&values.into_iter().collect(), key.v);
                 aggregated_result.results.push_back(stat_signel_v);
-                final_results.insert(key.class, aggregated_result);
+                final_results.insert(key.classes, aggregated_result);
             }
         }
     }
     final_results
 }
 
-fn generate_ml_csv(filename: &std::path::PathBuf, v:u32, results: &BTreeMap<Class, SimStatisticsMultiV>) -> std::io::Result<()> {
+fn generate_ml_csv(filename: &std::path::PathBuf, v:u32, results: &BTreeMap<Vec<Class>, SimStatisticsMultiV>) -> std::io::Result<()> {
     let mut file = File::create(filename)?;
-    SimStatisticsMultiV::write_header(v, &mut file);
+    let no_of_classes = results.keys().map(|x| x.len()).max().unwrap_or(1);
+    SimStatisticsMultiV::write_header(v, no_of_classes, &mut file);
 
     for (key, value) in results {
         println!("Writing to file statistics for {:?}", key);
-        value.write(&mut file);
+        value.write(no_of_classes, &mut file);
     }
 
     println!("Done");
@@ -274,6 +537,180 @@ fn generate_ml_csv(filename: &std::path::PathBuf, v:u32, results: &BTreeMap<Clas
     Ok(())
 }
 
+/// Parses traffic class description.
+/// Format: arrival_stream:arrival_e2d2:service_stream:service_e2d2:a:t[:sources[:priority[:t_min]]]
+fn parse_class(desc: &str) -> Option<SimClass> {
+    let params: Vec<&str> = desc.split(':').collect();
+    if params.len() < 6 || params.len() > 9 {
+        println!("Wrong class description \"{desc}\": 6 to 9 parameters are required");
+        return None;
+    }
+    let call_stream = StreamType::from_str(&params[0].to_lowercase()).ok()?;
+    let cs_e2_d2 = f64::from_str(params[1]).ok()?;
+    let service_stream = StreamType::from_str(&params[2].to_lowercase()).ok()?;
+    let ss_e2_d2 = f64::from_str(params[3]).ok()?;
+    let a = f64::from_str(params[4]).ok()?;
+    let t = usize::from_str(params[5]).ok()?;
+    let sources = match params.get(6) {
+        Some(sources) if !sources.is_empty() => Some(usize::from_str(sources).ok()?),
+        _ => None
+    };
+    let priority = match params.get(7) {
+        Some(priority) if !priority.is_empty() => u32::from_str(priority).ok()?,
+        _ => 0
+    };
+    let t_min = match params.get(8) {
+        Some(t_min) => Some(usize::from_str(t_min).ok()?),
+        None => None
+    };
+    if let Err(reason) = call_stream.check_e2d2(cs_e2_d2).and(service_stream.check_e2d2(ss_e2_d2)) {
+        println!("Wrong class description \"{desc}\": {reason}");
+        return None;
+    }
+
+    let mut tr_class = SimClass::new(call_stream, service_stream, a, cs_e2_d2, 1f64, ss_e2_d2, t)?;
+    tr_class.tr_class.set_sources(sources);
+    tr_class.tr_class.set_priority(priority);
+    tr_class.tr_class.set_t_min(t_min);
+    Some(tr_class)
+}
+
+fn prepare_queue(args: &SimulateArgs) -> Option<QueueDescription> {
+    if !args.queue && args.queue_capacity.is_none() {
+        return None;
+    }
+    let patience = args.patience_stream.as_ref().map(|stream| StreamParams::new(
+        StreamType::from_str(&stream.to_lowercase()).expect("Failed"),
+        args.patience_intensity, args.patience_e2_d2));
+
+    if let Some(params) = &patience {
+        params.get_stream().expect("Wrong patience stream parameters");
+    }
+    Some(QueueDescription { capacity: args.queue_capacity, patience: patience })
+}
+
+fn prepare_retrial(args: &SimulateArgs) -> Option<RetrialDescription> {
+    let stream = args.retrial_stream.as_ref()?;
+    let delay = StreamParams::new(
+        StreamType::from_str(&stream.to_lowercase()).expect("Failed"),
+        args.retrial_intensity, args.retrial_e2_d2);
+
+    delay.get_stream().expect("Wrong retrial stream parameters");
+    Some(RetrialDescription { delay: delay, persistence: args.persistence })
+}
+
+fn prepare_breakdown(args: &SimulateArgs) -> Option<BreakdownDescription> {
+    let stream = args.failure_stream.as_ref()?;
+    let failure = StreamParams::new(
+        StreamType::from_str(&stream.to_lowercase()).expect("Failed"),
+        args.failure_intensity, args.failure_e2_d2);
+    let repair = StreamParams::new(
+        StreamType::from_str(&args.repair_stream.to_lowercase()).expect("Failed"),
+        args.repair_intensity, args.repair_e2_d2);
+
+    failure.get_stream().expect("Wrong failure stream parameters");
+    repair.get_stream().expect("Wrong repair stream parameters");
+    Some(BreakdownDescription {
+        failure: failure,
+        repair: repair,
+        policy: FailurePolicy::from_str(&args.failure_policy.to_lowercase()).expect("Failed to parse failure policy")
+    })
+}
+
+/// Reads piecewise-constant intensity profile, every row is "start_time,multiplier"
+fn read_profile(filename: &std::path::PathBuf, period: Option<f64>) -> std::io::Result<IntensityProfile> {
+    let invalid = |line: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Wrong profile step \"{line}\""));
+    let mut steps = Vec::new();
+    for line in std::fs::read_to_string(filename)?.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (start, multiplier) = line.split_once(',').ok_or_else(|| invalid(line))?;
+        steps.push((f64::from_str(start.trim()).map_err(|_| invalid(line))?,
+                    f64::from_str(multiplier.trim()).map_err(|_| invalid(line))?));
+    }
+    Ok(IntensityProfile::PiecewiseConstant { steps: steps, period: period })
+}
+
+fn prepare_profile(args: &SimulateArgs) -> Option<IntensityProfile> {
+    let profile = match (&args.profile_csv, args.profile_sinusoid_period) {
+        (Some(filename), _) => read_profile(filename, args.profile_period).expect("Failed to read intensity profile"),
+        (None, Some(period)) => IntensityProfile::Sinusoidal {
+            period: period,
+            amplitude: args.profile_sinusoid_amplitude,
+            phase: args.profile_sinusoid_phase
+        },
+        (None, None) => return None
+    };
+    assert!(profile.is_valid(), "Wrong intensity profile {:?}", profile);
+    Some(profile)
+}
+
+fn prepare_arrival_map(args: &SimulateArgs) -> Option<MarkovArrivalDescription> {
+    let arrival_map = match (&args.arrival_map_file, &args.mmpp) {
+        (Some(filename), _) => {
+            let file = File::open(filename).expect("Failed to open Markovian arrival process file");
+            serde_json::from_reader(file).expect("Failed to read Markovian arrival process")
+        }
+        (None, Some(mmpp)) => MarkovArrivalDescription::from_str(mmpp).expect("Failed to parse MMPP parameters"),
+        (None, None) => return None
+    };
+    assert!(arrival_map.is_valid(), "Wrong Markovian arrival process {:?}", arrival_map);
+    Some(arrival_map)
+}
+
+fn prepare_trace(path: &Option<String>, column: Option<usize>, mode: &str) -> Option<TraceDescription> {
+    let mode = TraceMode::from_str(&mode.to_lowercase()).expect("Failed to parse trace mode");
+    let trace = TraceDescription::load(path.as_ref()?, column, mode).expect("Failed to read trace");
+    println!("Trace {}: {} samples, mean {}, E²/D² {}, hash {}", trace.path, trace.no_of_samples, trace.mean, trace.e2d2, trace.hash);
+    Some(trace)
+}
+
+fn prepare_on_off(args: &SimulateArgs) -> Option<OnOffDescription> {
+    let on_off = OnOffDescription {
+        sources: args.on_off_sources?,
+        hurst: args.hurst,
+        mean_on: args.mean_on,
+        mean_off: args.mean_off
+    };
+    assert!(on_off.is_valid(), "Wrong ON/OFF sources {:?}", on_off);
+    Some(on_off)
+}
+
+fn prepare_batch(args: &SimulateArgs) -> Option<BatchDescription> {
+    let size = args.batch_size.as_ref()?;
+    Some(BatchDescription {
+        size: BatchSize::from_str(&size.to_lowercase()).expect("Failed to parse batch size"),
+        policy: BatchPolicy::from_str(&args.batch_policy.to_lowercase()).expect("Failed to parse batch policy")
+    })
+}
+
+fn prepare_sleep(args: &SimulateArgs) -> Option<SleepDescription> {
+    let stream = args.setup_stream.as_ref()?;
+    let setup = StreamParams::new(
+        StreamType::from_str(&stream.to_lowercase()).expect("Failed"),
+        args.setup_intensity, args.setup_e2_d2);
+
+    setup.get_stream().expect("Wrong setup stream parameters");
+    Some(SleepDescription {
+        setup: setup,
+        awake: args.awake_servers,
+        policy: SleepPolicy::from_str(&args.sleep_policy.to_lowercase()).expect("Failed to parse sleep policy")
+    })
+}
+
+/// Rates of `v` servers, the list of rates is repeated
+fn prepare_servers(args: &SimulateArgs, v: usize) -> Option<ServersDescription> {
+    let rates: Vec<f64> = args.server_rates.as_ref()?.split(',')
+        .map(|rate| f64::from_str(rate.trim()).expect("Failed to parse server rate"))
+        .collect();
+    Some(ServersDescription {
+        rates: rates.iter().cycle().take(v).copied().collect(),
+        hunting: HuntingPolicy::from_str(&args.hunting.to_lowercase()).expect("Failed to parse hunting policy")
+    })
+}
+
+fn prepare_windows(args: &SimulateArgs) -> Option<WindowsDescription> {
+    args.window_length.map(|length| WindowsDescription { length: length, period: args.window_period })
+}
+
 /// Prepares tasks.
 /// First check if the results are available in database
 fn prepare_tasks(args: &SimulateArgs, db: &Option<mongodb::sync::Database>, results: &mut BTreeMultiMap<ModelDescription, StatisticsFinalized>) -> LinkedList<SimulationTask> {
@@ -284,24 +721,84 @@ fn prepare_tasks(args: &SimulateArgs, db: &Option<mongodb::sync::Database>, resu
     let cs_e2_d2_col:Vec<f64> = flot::range(args.cs_e2_d2_min, args.cs_e2_d2_max + args.cs_e2_d2_delta, args.cs_e2_d2_delta).collect();
     let ss_e2_d2_col:Vec<f64> = flot::range(args.ss_e2_d2_min, args.ss_e2_d2_max + args.ss_e2_d2_delta, args.ss_e2_d2_delta).collect();
     let a_col: Vec<f64> = flot::range(args.a_min, args.a_max + args.a_delta, args.a_delta).collect();
+    let t_col = args.demand.clone();
+    let background_classes: Vec<SimClass> = args.background_class.iter()
+        .map(|desc| parse_class(desc).expect("Failed to parse background class"))
+        .collect();
+    let queue = prepare_queue(args);
+    let retrial = prepare_retrial(args);
+    let breakdown = prepare_breakdown(args);
+    let profile = prepare_profile(args);
+    let batch = prepare_batch(args);
+    let arrival_map = prepare_arrival_map(args);
+    let on_off = prepare_on_off(args);
+    let arrival_trace = prepare_trace(&args.arrival_trace, args.arrival_trace_column, &args.trace_mode);
+    let service_trace = prepare_trace(&args.service_trace, args.service_trace_column, &args.trace_mode);
+    let windows = prepare_windows(args);
+    let sleep = prepare_sleep(args);
+    let service_rate = args.service_rate.as_ref().map(|rate|
+        ServiceRateDescription::from_str(&rate.to_lowercase()).expect("Failed to parse service rate"));
+    let preemption = args.preemption.as_ref().map(|policy|
+        PreemptionPolicy::from_str(&policy.to_lowercase()).expect("Failed to parse preemption policy"));
 
     let mut no_off_skipped_classes = 0;
     let mut no_off_stored_tasks_before = 0;
     let mut no_off_total_tasks_before = 0;
 
-    for (cur_call_stream, cur_serv_stream, cs_e2_d2, ss_e2_d2, a) in
-    cartesian!(call_streams.iter(), serv_streams.iter(), cs_e2_d2_col.iter(), ss_e2_d2_col.iter(), a_col.iter())
+    for (cur_call_stream, cur_serv_stream, cs_e2_d2, ss_e2_d2, a, t) in
+    cartesian!(call_streams.iter(), serv_streams.iter(), cs_e2_d2_col.iter(), ss_e2_d2_col.iter(), a_col.iter(), t_col.iter())
     {
         // Prepare Streams and write its params
         let call_stream = StreamType::from_str(&cur_call_stream.to_lowercase()).expect("Failed");
         let service_stream = StreamType::from_str(&cur_serv_stream.to_lowercase()).expect("Failed");
 
-        if let Some(tr_class) = SimClass::new(
-            call_stream, service_stream,
-            *a, *cs_e2_d2, 1f64, *ss_e2_d2) {
+        // E²/D² of the trace stream is taken from the trace and deterministic stream has E²/D² = ∞,
+        // the same class would repeat for every grid value
+        let fixed_e2d2 = |stream: StreamType| stream == StreamType::Trace || stream == StreamType::Deterministic;
+        if (fixed_e2d2(call_stream) && cs_e2_d2 != &cs_e2_d2_col[0]) ||
+            (fixed_e2d2(service_stream) && ss_e2_d2 != &ss_e2_d2_col[0]) {
+            continue;
+        }
+        let cs_e2_d2 = if call_stream == StreamType::Deterministic { f64::INFINITY } else { *cs_e2_d2 };
+        let ss_e2_d2 = if service_stream == StreamType::Deterministic { f64::INFINITY } else { *ss_e2_d2 };
+
+        let validity = call_stream.check_e2d2(cs_e2_d2).map_err(|e| format!("arrival: {e}"))
+            .and(service_stream.check_e2d2(ss_e2_d2).map_err(|e| format!("service: {e}")));
+        if let Err(reason) = validity {
+            println!("Skipped class a={}, t={}, {}", a, t, reason);
+            no_off_skipped_classes += 1;
+            continue;
+        }
+
+        let mut tr_class = Class::new(call_stream, service_stream, *a, cs_e2_d2, 1f64, ss_e2_d2, *t);
+        tr_class.set_sources(args.sources);
+        tr_class.set_priority(args.priority);
+        tr_class.set_t_min(args.min_demand);
+        tr_class.set_pareto_shape(args.pareto_shape);
+        tr_class.set_profile(profile.clone());
+        tr_class.set_batch(batch.clone());
+        tr_class.set_arrival_map(arrival_map.clone());
+        tr_class.set_arrival_on_off(on_off.clone());
+        if call_stream == StreamType::Trace {
+            tr_class.set_arrival_trace(Some(arrival_trace.clone().expect("Trace arrival stream needs --arrival-trace file")));
+        }
+        if service_stream == StreamType::Trace {
+            tr_class.set_service_trace(Some(service_trace.clone().expect("Trace service stream needs --service-trace file")));
+        }
+
+        if let Some(tr_class) = SimClass::from_class(&tr_class) {
+
+            let mut tr_classes = vec![tr_class];
+            tr_classes.extend(background_classes.iter().cloned());
+            let classes: Vec<Class> = tr_classes.iter().map(|x| x.tr_class.clone()).collect();
 
             for v in 1..args.v + 1 {
-                let model = ModelDescription{v:v as usize, class:tr_class.tr_class.clone()};
+                let model = ModelDescription{v:v as usize, classes:classes.clone(), queue: queue, retrial: retrial, preemption: preemption, breakdown: breakdown, windows: windows, sleep: sleep, servers: prepare_servers(args, v as usize), service_rate: service_rate.clone()};
+                if let Err(reason) = model.check() {
+                    println!("Skipped class a={}, t={}, v={}, {}", a, t, v, reason);
+                    no_off_skipped_classes += 1;
+                    continue;
+                }
                 let mut sim_experiments = match &db {
                     Some(_db) => read_finilized_statistics(&model, &_db, args.mim_state_cntr),
                     None => LinkedList::new()
@@ -309,7 +806,7 @@ fn prepare_tasks(args: &SimulateArgs, db: &Option<mongodb::sync::Database>, resu
 
                 let no_of_ready_statistics= sim_experiments.len();
                 for sim_result in sim_experiments {
-                    results.insert(ModelDescription{class: tr_class.tr_class, v: v as usize}, sim_result);
+                    results.insert(model.clone(), sim_result);
                 }
 
                 no_off_stored_tasks_before += no_of_ready_statistics;
@@ -317,43 +814,58 @@ fn prepare_tasks(args: &SimulateArgs, db: &Option<mongodb::sync::Database>, resu
 
                 for sim_no in no_of_ready_statistics..args.no_of_series {
                     tasks.push_back(SimulationTask {
-                        tr_class: tr_class,
-                        v: v as usize,
+                        tr_classes: tr_classes.clone(),
+                        model: model.clone(),
                         mim_state_cntr: args.mim_state_cntr,
                         sim_no: (sim_no - no_of_ready_statistics) as u32
                     });
                 }
             }
         } else {
+            println!("Skipped class a={}, t={}, failed to prepare streams", a, t);
             no_off_skipped_classes += 1;
         }
     }
+    if no_off_skipped_classes > 0 {
+        println!("Number of skipped classes: {}", no_off_skipped_classes);
+    }
     println!("Number od tasks to do: {}, number of stored (skipped) tasks {}", tasks.len(), no_off_stored_tasks_before);
 
     tasks
 }
 
 fn calculate(no_of_threads:u32, mut tasks: LinkedList<SimulationTask>, db: &mut Option<mongodb::sync::Database>, results: &mut BTreeMultiMap<ModelDescription, StatisticsFinalized>) {
-    let mut workers: LinkedList <JoinHandle<(ModelDescription, StatisticsFinalized)>> = LinkedList::new();
+    let mut workers: LinkedList <JoinHandle<(ModelDescription, Option<StatisticsFinalized>)>> = LinkedList::new();
     while !tasks.is_empty() {
         let mut task_no = 0;
         while task_no < no_of_threads && !tasks.is_empty() {
             let cur_task = tasks.pop_front().unwrap();
             workers.push_back(thread::spawn(move || {
-                println!("Simulation a={}, arrival stream {}:{}, service stream {}:{}", cur_task.tr_class.get_a(), cur_task.tr_class.get_str_new_desc(), cur_task.tr_class.get_new_e2d2(), cur_task.tr_class.get_str_end_desc(), cur_task.tr_class.get_end_e2d2());
+                for tr_class in &cur_task.tr_classes {
+                    println!("Simulation a={}, t={}, sources={:?}, priority={}, arrival stream {}:{}, service stream {}:{}", tr_class.get_a(), tr_class.get_t(), tr_class.get_sources(), tr_class.get_priority(), tr_class.get_str_new_desc(), tr_class.get_new_e2d2(), tr_class.get_str_end_desc(), tr_class.get_end_e2d2());
+                }
                 let start = Instant::now();
-                let result = sim::simulation(cur_task.v, cur_task.tr_class, cur_task.mim_state_cntr);
+                let result = match sim::simulation(&cur_task.model, cur_task.tr_classes, cur_task.mim_state_cntr) {
+                    Ok(result) => result,
+                    Err(reason) => {
+                        println!("v={}: simulation failed, {}", cur_task.model.v, reason);
+                        return (cur_task.model, None);
+                    }
+                };
                 let duration = start.elapsed();
                 let pefromance = (result.no_of_events as f64) / duration.as_micros() as f64;
-                println!("v={}: performance {:.3} events/µs, no of events : {} ", cur_task.v, pefromance, result.no_of_events);
-                (ModelDescription{class:cur_task.tr_class.tr_class, v: cur_task.v }, result)
+                println!("v={}: performance {:.3} events/µs, no of events : {} ", cur_task.model.v, pefromance, result.no_of_events);
+                (cur_task.model, Some(result))
             }));
             task_no += 1;
         }
 
         while !workers.is_empty() {
             let single_worker = workers.pop_front().unwrap();
-            let (key, value) = single_worker.join().unwrap();
+            let (key, value) = match single_worker.join().unwrap() {
+                (key, Some(value)) => (key, value),
+                (_, None) => continue
+            };
 
             if let Some(db_val) = db {
                 let str = serde_json::to_string(&value).unwrap();
@@ -367,6 +879,69 @@ fn calculate(no_of_threads:u32, mut tasks: LinkedList<SimulationTask>, db: &mut
     }
 }
 
+fn read_topology(filename: &std::path::PathBuf) -> std::io::Result<(TopologyDescription, Vec<SimClass>)> {
+    let file = File::open(filename)?;
+    let config: TopologyConfig = serde_json::from_reader(file)?;
+    let topology = config.topology()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Wrong topology description: {e}")))?;
+    let tr_classes: Vec<SimClass> = topology.classes.iter()
+        .map(|tr_class| SimClass::from_class(tr_class).expect("Wrong traffic class parameters"))
+        .collect();
+    if let Err(e) = Simulator::with_topology(&tr_classes, &topology) {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Wrong topology description: {e}")));
+    }
+    Ok((topology, tr_classes))
+}
+
+fn calculate_topology(args: &SimulateTopologyArgs, topology: &TopologyDescription, tr_classes: &Vec<SimClass>, db: &mut Option<mongodb::sync::Database>) -> Vec<TopologyStatistics> {
+    let mut results = Vec::new();
+    let mut series_left = args.no_of_series;
+    while series_left > 0 {
+        let mut workers: LinkedList <JoinHandle<TopologyStatistics>> = LinkedList::new();
+        while (workers.len() as u32) < args.threads_no && series_left > 0 {
+            let cur_topology = topology.clone();
+            let cur_classes = tr_classes.clone();
+            let mim_state_cntr = args.mim_state_cntr;
+            workers.push_back(thread::spawn(move || {
+                let start = Instant::now();
+                let result = sim::topology_simulation(&cur_topology, cur_classes, mim_state_cntr)
+                    .expect("Topology is checked when it is read");
+                let duration = start.elapsed();
+                let pefromance = (result.no_of_events as f64) / duration.as_micros() as f64;
+                println!("Groups {:?}: performance {:.3} events/µs, no of events : {} ", cur_topology.groups, pefromance, result.no_of_events);
+                result
+            }));
+            series_left -= 1;
+        }
+
+        while let Some(single_worker) = workers.pop_front() {
+            let value = single_worker.join().unwrap();
+            if let Some(db_val) = db {
+                if let Err(err) = value.write_mongo(topology, db_val) {
+                    println!("Failed to save results: {err}");
+                }
+            }
+            results.push(value);
+        }
+    }
+    results
+}
+
+fn mongo_open_configured_database(cfg: MyConfig) -> Option<mongodb::sync::Database> {
+    let mut credentials  = Credential::default();
+    credentials.username = cfg.mongo_user;
+    credentials.source = cfg.mongo_auth_database;
+    credentials.password = cfg.mongo_password;
+    credentials.mechanism = match cfg.mongo_auth_mechanism {
+        Some(CfgAuthMechanism::ScramSha1) => { Some(AuthMechanism::ScramSha1)},
+        Some(CfgAuthMechanism::ScramSha265) => { Some(AuthMechanism::ScramSha256)},
+        Some(CfgAuthMechanism::None) => { None },
+        None => { None }
+    };
+
+    mongo_open_database(&cfg.mongo_uri, &cfg.mongo_database, Some(credentials))
+}
+
 fn main() -> std::io::Result<()>
 {
     let args = Cli::parse();
@@ -380,18 +955,7 @@ fn main() -> std::io::Result<()>
 
     match &args.command {
         Some(Commands::Simulate(args)) => {
-            let mut credentials  = Credential::default();
-            credentials.username = cfg.mongo_user;
-            credentials.source = cfg.mongo_auth_database;
-            credentials.password = cfg.mongo_password;
-            credentials.mechanism = match cfg.mongo_auth_mechanism {
-                Some(CfgAuthMechanism::ScramSha1) => { Some(AuthMechanism::ScramSha1)},
-                Some(CfgAuthMechanism::ScramSha265) => { Some(AuthMechanism::ScramSha256)},
-                Some(CfgAuthMechanism::None) => { None },
-                None => { None }
-            };
-
-            let mut db: Option<mongodb::sync::Database> = mongo_open_database(&cfg.mongo_uri, &cfg.mongo_database, Some(credentials));
+            let mut db: Option<mongodb::sync::Database> = mongo_open_configured_database(cfg);
             let mut results: BTreeMultiMap<ModelDescription, StatisticsFinalized> = BTreeMultiMap::new();
 
             let mut tasks = prepare_tasks(&args, &db, &mut results);
@@ -401,6 +965,16 @@ fn main() -> std::io::Result<()>
 
             generate_ml_csv(&args.output_path, args.v, &final_results)
         }
+        Some(Commands::SimulateTopology(args)) => {
+            let (topology, tr_classes) = read_topology(&args.config)?;
+            let mut db: Option<mongodb::sync::Database> = mongo_open_configured_database(cfg);
+
+            let results = calculate_topology(&args, &topology, &tr_classes, &mut db);
+
+            let file = File::create(&args.output_path)?;
+            serde_json::to_writer_pretty(file, &results)?;
+            Ok(())
+        }
         Some(Commands::ConfigureMongo (mongo_config)) => {
             cfg.mongo_uri = mongo_config.mongo_uri.to_string();
             cfg.mongo_database = mongo_config.mongo_database.to_string();
@@ -3,17 +3,19 @@ pub mod model;
 
 use std::collections::LinkedList;
 use crate::sim::model::class::sim_class::SimClass;
+use crate::sim::model::system::ModelDescription;
+use crate::sim::model::topology::TopologyDescription;
 use crate::sim::simulator::Simulator;
-use crate::sim::simulator::single_statistics::{StatisticsFinalized};
+use crate::sim::simulator::single_statistics::{StatisticsFinalized, TopologyStatistics};
 use crate::sim::simulator::simulations_statistics::StatisticsMultiSimulations;
 
 
 //#![feature(map_first_last)]
 
-pub fn simulation_all_series(v: usize, tr_class:SimClass, min_state_cntr: u32, no_of_ser: usize)
-                           -> StatisticsMultiSimulations
+pub fn simulation_all_series(model: &ModelDescription, tr_classes:Vec<SimClass>, min_state_cntr: u32, no_of_ser: usize)
+                           -> Result<StatisticsMultiSimulations, String>
 {
-    let mut systems = vec![Simulator::new(&tr_class, v); no_of_ser];
+    let mut systems = vec![Simulator::new(&tr_classes, model)?; no_of_ser];
 
     let mut statistics: LinkedList<StatisticsFinalized> = LinkedList::new();
 
@@ -27,15 +29,25 @@ pub fn simulation_all_series(v: usize, tr_class:SimClass, min_state_cntr: u32, n
     }
     //TODO mongo write to database
 
-    StatisticsMultiSimulations::statistics_proc(&statistics, v)
+    Ok(StatisticsMultiSimulations::statistics_proc(&statistics, model.v))
 }
 
-pub fn simulation(v: usize, tr_class:SimClass, min_state_cntr: u32)
-                           -> StatisticsFinalized
+pub fn simulation(model: &ModelDescription, tr_classes:Vec<SimClass>, min_state_cntr: u32)
+                           -> Result<StatisticsFinalized, String>
 {
-    let mut system = Simulator::new(&tr_class, v);
+    let mut system = Simulator::new(&tr_classes, model)?;
 
     system.prepare_simulation();
     system.simulate_with_statistics(min_state_cntr);
-    system.prepare_statistics()
-}
\ No newline at end of file
+    Ok(system.prepare_statistics())
+}
+
+pub fn topology_simulation(topology: &TopologyDescription, tr_classes:Vec<SimClass>, min_state_cntr: u32)
+                           -> Result<TopologyStatistics, String>
+{
+    let mut system = Simulator::with_topology(&tr_classes, topology)?;
+
+    system.prepare_simulation();
+    system.simulate_with_statistics(min_state_cntr);
+    Ok(system.prepare_topology_statistics())
+}
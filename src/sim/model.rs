@@ -1,5 +1,11 @@
 use crate::sim::model::class::Class;
 
+pub mod batch;
 pub mod class;
+pub mod markov;
+pub mod onoff;
+pub mod profile;
 pub mod system;
+pub mod topology;
+pub mod trace;
 
@@ -0,0 +1,90 @@
+use std::str::FromStr;
+use rand::Rng;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand_distr::{Geometric, Poisson};
+use serde::{Deserialize, Serialize};
+
+/// Distribution of the number of calls that arrive at the same time
+#[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Debug)]
+pub enum BatchSize {
+    Fixed(usize),
+    /// Geometric distribution on 1, 2, ... with given mean
+    Geometric { mean: f64 },
+    /// Poisson distribution shifted by 1, so the batch is never empty
+    Poisson { mean: f64 },
+    /// Probabilities (or weights) of batch sizes 1, 2, ...
+    Empirical(Vec<f64>)
+}
+
+/// How the batch is admitted when it doesn't fit entirely into the group
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum BatchPolicy {
+    /// Whole batch is lost if any of its calls doesn't fit
+    AllOrNothing,
+    /// Calls of the batch are offered one by one, the ones that don't fit are lost
+    #[default]
+    Partial
+}
+
+/// Batch (bulk) arrivals, every arrival brings several calls of the class
+#[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Debug)]
+pub struct BatchDescription {
+    pub size: BatchSize,
+    #[serde(default)]
+    pub policy: BatchPolicy
+}
+
+impl BatchSize {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            BatchSize::Fixed(size) => *size > 0,
+            BatchSize::Geometric { mean } | BatchSize::Poisson { mean } => *mean >= 1f64,
+            BatchSize::Empirical(weights) => weights.iter().all(|weight| *weight >= 0f64)
+                && weights.iter().any(|weight| *weight > 0f64)
+        }
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        match self {
+            BatchSize::Fixed(size) => *size,
+            // Number of failures before the first success
+            BatchSize::Geometric { mean } =>
+                1 + Geometric::new(1f64 / mean).expect("Wrong geometric batch size").sample(rng) as usize,
+            BatchSize::Poisson { mean } if *mean == 1f64 => 1,
+            BatchSize::Poisson { mean } =>
+                1 + Poisson::new(mean - 1f64).expect("Wrong Poisson batch size").sample(rng) as usize,
+            BatchSize::Empirical(weights) =>
+                1 + WeightedIndex::new(weights).expect("Wrong empirical batch size").sample(rng)
+        }
+    }
+}
+
+/// Format: fixed:size, geometric:mean, poisson:mean or empirical:p1,p2,...
+impl FromStr for BatchSize {
+    type Err = ();
+    fn from_str(input: &str) -> Result<BatchSize, Self::Err> {
+        let (kind, params) = input.split_once(':').ok_or(())?;
+        let result = match kind {
+            "fixed" => BatchSize::Fixed(usize::from_str(params).map_err(|_| ())?),
+            "geometric" => BatchSize::Geometric { mean: f64::from_str(params).map_err(|_| ())? },
+            "poisson" => BatchSize::Poisson { mean: f64::from_str(params).map_err(|_| ())? },
+            "empirical" => BatchSize::Empirical(params.split(',')
+                .map(|weight| f64::from_str(weight.trim()).map_err(|_| ()))
+                .collect::<Result<Vec<f64>, ()>>()?),
+            _ => return Err(())
+        };
+        if result.is_valid() { Ok(result) } else { Err(()) }
+    }
+}
+
+impl FromStr for BatchPolicy {
+    type Err = ();
+    fn from_str(input: &str) -> Result<BatchPolicy, Self::Err> {
+        match input {
+            "all"     => Ok(BatchPolicy::AllOrNothing),
+            "partial" => Ok(BatchPolicy::Partial),
+            _         => Err(()),
+        }
+    }
+}
@@ -1,9 +1,17 @@
 use std::cmp::Ordering;
-use approx::relative_ne;
-use rand_distr::{Exp, Gamma, Pareto, Uniform};
-use serde::{Deserialize, Serialize};
+use approx::{relative_eq, relative_ne};
+use rand::prelude::Distribution;
+use rand::Rng;
+use rand_distr::{Exp, Gamma, LogNormal, Pareto, Uniform, Weibull};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de;
 use std::str::FromStr;
 use float_cmp::{ApproxEq, F64Margin};
+use crate::sim::model::batch::BatchDescription;
+use crate::sim::model::markov::MarkovArrivalDescription;
+use crate::sim::model::onoff::OnOffDescription;
+use crate::sim::model::profile::IntensityProfile;
+use crate::sim::model::trace::{TraceDescription, TraceStream};
 
 pub mod utils;
 pub mod sim_class;
@@ -13,17 +21,57 @@ pub enum StreamType {
     Poisson,
     Uniform,
     Gamma,
-    Pareto
+    Pareto,
+    /// Erlang-k, mixed with Erlang-(k-1) if E²/D² is not integer
+    Erlang,
+    /// Two-phase hyperexponential with balanced means
+    Hyperexponential,
+    /// Two-phase Coxian
+    Coxian,
+    Lognormal,
+    Weibull,
+    /// Pareto with shape 1.5 bounded from above
+    TruncatedPareto,
+    /// Constant time, E²/D² is ∞
+    Deterministic,
+    /// Constant plus exponential time
+    ShiftedExponential,
+    /// Recorded times, E²/D² is taken from the trace
+    Trace
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum StreamOfEvents {
     Poisson (Exp<f64>),
     Uniform (Uniform<f64>),
     Gamma (Gamma<f64>),
     Pareto (Pareto<f64>),
+    /// Sum of `phases` exponential phases, with probability `p` one phase less
+    Erlang { phases: usize, p: f64, phase: Exp<f64> },
+    /// The first phase with probability `p`, otherwise the second one
+    Hyperexponential { p: f64, phases: [Exp<f64>; 2] },
+    /// The first phase, followed by the second one with probability `p`
+    Coxian { p: f64, phases: [Exp<f64>; 2] },
+    Lognormal (LogNormal<f64>),
+    Weibull (Weibull<f64>),
+    /// Pareto distribution with given `shape` restricted to range `low`..`high`
+    TruncatedPareto { low: f64, high: f64, shape: f64 },
+    Deterministic (f64),
+    /// Exponential time after the constant `shift`
+    ShiftedExponential { shift: f64, phase: Exp<f64> },
+    Trace (TraceStream),
 }
-#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+/// Stream described by its type, intensity (1/E) and E²/D²
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, PartialOrd, Debug)]
+pub struct StreamParams
+{
+    pub stream_type: StreamType,
+    pub intensity: f64,
+    #[serde(with = "e2d2_format")]
+    pub e2d2: f64
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Class
 {
     /// Traffic offered per system, capacity doesn't matter
@@ -33,9 +81,210 @@ pub struct Class
     mu: f64,
 
     arrival_stream_type: StreamType,
+    #[serde(with = "e2d2_format")]
     arrival_e2d2       : f64,
     service_stream_type: StreamType,
-    service_e2d2       : f64
+    #[serde(with = "e2d2_format")]
+    service_e2d2       : f64,
+
+    /// Number of basic bandwidth units (BBU) demanded by a single call, 1 if not given
+    #[serde(default = "default_t", deserialize_with = "deserialize_t")]
+    t: usize,
+
+    /// Number of traffic sources, None for infinite number of sources.
+    /// Finite source generates new call after think time described by the arrival stream.
+    /// The source is silent while its call is in the system.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sources: Option<usize>,
+
+    /// Shape of truncated Pareto streams of the class, `utils::TRUNCATED_PARETO_SHAPE` if None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pareto_shape: Option<f64>,
+
+    /// Priority of the class, calls of higher priority can preempt calls of lower priority
+    #[serde(default, skip_serializing_if = "is_default_priority")]
+    priority: u32,
+
+    /// Minimum number of BBUs of elastic call, None if the call doesn't compress.
+    /// Elastic call occupies from `t_min` to `t` BBUs, its service time stretches when it is compressed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    t_min: Option<usize>,
+
+    /// Arrival intensity multiplier over simulated time, None for stationary arrival stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<IntensityProfile>,
+
+    /// Number of calls that arrive at once, None if calls arrive one by one.
+    /// Offered traffic `a` describes arrivals of batches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    batch: Option<BatchDescription>,
+
+    /// Markovian arrival process scaled to the arrival intensity, it replaces the renewal arrival stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arrival_map: Option<MarkovArrivalDescription>,
+
+    /// Aggregated heavy-tailed ON/OFF sources with the arrival intensity of the class,
+    /// they replace the renewal arrival stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arrival_on_off: Option<OnOffDescription>,
+
+    /// Recorded inter-arrival times of the trace arrival stream, scaled to the arrival intensity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arrival_trace: Option<TraceDescription>,
+
+    /// Recorded holding times of the trace service stream, scaled to the service intensity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    service_trace: Option<TraceDescription>
+}
+
+fn is_default_priority(priority: &u32) -> bool {
+    *priority == 0
+}
+
+impl StreamType {
+    /// Checks if the stream can have given E²/D², otherwise describes the valid range.
+    /// Limits are compared with tolerance, as E²/D² may be computed from the mean and variance.
+    pub fn check_e2d2(&self, e2d2: f64) -> Result<(), String> {
+        let (min, max) = match self {
+            StreamType::Poisson => (1f64, 1f64),
+            StreamType::Uniform => (3f64, f64::INFINITY),
+            StreamType::Erlang | StreamType::ShiftedExponential => (1f64, f64::INFINITY),
+            StreamType::Hyperexponential => (0f64, 1f64),
+            StreamType::Coxian => (0f64, 2f64),
+            StreamType::Gamma | StreamType::Pareto | StreamType::Lognormal | StreamType::Weibull |
+            StreamType::TruncatedPareto | StreamType::Trace => (0f64, f64::INFINITY),
+            StreamType::Deterministic => (f64::INFINITY, f64::INFINITY)
+        };
+        let valid = if min == max {
+            !relative_ne!(e2d2, min)
+        } else {
+            e2d2 > 0f64 && e2d2.is_finite()
+                && (e2d2 >= min || relative_eq!(e2d2, min)) && (e2d2 <= max || relative_eq!(e2d2, max))
+        };
+        match valid {
+            true => Ok(()),
+            false if min == max => Err(format!("{} stream needs E²/D² = {}, got {}", Class::get_str_desc(self), min, e2d2)),
+            false => Err(format!("{} stream needs E²/D² in range {}..{}, got {}", Class::get_str_desc(self), min, max, e2d2))
+        }
+    }
+}
+
+impl StreamOfEvents {
+    /// Sample of the stream, replayed trace starts at random sample, because its position isn't kept
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match self {
+            StreamOfEvents::Poisson(distr) => distr.sample(rng),
+            StreamOfEvents::Uniform(distr) => distr.sample(rng),
+            StreamOfEvents::Gamma(distr) => distr.sample(rng),
+            StreamOfEvents::Pareto(distr) => distr.sample(rng),
+            StreamOfEvents::Erlang { phases, p, phase } => {
+                let phases = if rng.gen::<f64>() < *p { phases - 1 } else { *phases };
+                (0..phases).map(|_| phase.sample(rng)).sum()
+            },
+            StreamOfEvents::Hyperexponential { p, phases } =>
+                if rng.gen::<f64>() < *p { phases[0].sample(rng) } else { phases[1].sample(rng) },
+            StreamOfEvents::Coxian { p, phases } =>
+                phases[0].sample(rng) + if rng.gen::<f64>() < *p { phases[1].sample(rng) } else { 0f64 },
+            StreamOfEvents::Lognormal(distr) => distr.sample(rng),
+            StreamOfEvents::Weibull(distr) => distr.sample(rng),
+            // Inverse of the distribution function
+            StreamOfEvents::TruncatedPareto { low, high, shape } => {
+                let tail = 1f64 - rng.gen::<f64>() * (1f64 - (low / high).powf(*shape));
+                low / tail.powf(1f64 / shape)
+            },
+            StreamOfEvents::Deterministic(time) => *time,
+            StreamOfEvents::ShiftedExponential { shift, phase } => shift + phase.sample(rng),
+            StreamOfEvents::Trace(trace) => trace.sample(rng, &mut None),
+        }
+    }
+
+    /// Sample of the stream, `position` of the replayed trace is kept by the caller
+    pub fn sample_at<R: Rng + ?Sized>(&self, rng: &mut R, position: &mut Option<usize>) -> f64 {
+        match self {
+            StreamOfEvents::Trace(trace) => trace.sample(rng, position),
+            _ => self.sample(rng)
+        }
+    }
+}
+
+impl StreamParams {
+    /// E²/D² of the deterministic stream is always infinite, given `e2d2` is ignored
+    pub fn new(stream_type: StreamType, intensity: f64, e2d2: f64) -> Self {
+        let e2d2 = if stream_type == StreamType::Deterministic { f64::INFINITY } else { e2d2 };
+        StreamParams { stream_type, intensity, e2d2 }
+    }
+
+    /// Creates stream of events, returns None if parameters are not valid for the stream type
+    pub fn get_stream(&self) -> Option<StreamOfEvents> {
+        let (mean, variance) = utils::get_e_d(self.intensity, self.e2d2);
+        sim_class::SimClass::try_get_stream(self.stream_type, mean, variance, utils::TRUNCATED_PARETO_SHAPE)
+    }
+}
+
+impl Eq for StreamParams {}
+
+/// Marker of the infinite E²/D² (deterministic stream), JSON has no number for it
+const INFINITE_E2D2: &str = "inf";
+
+/// E²/D² as it is written. Infinite E²/D² is written as `INFINITE_E2D2`, null is read as infinite.
+#[derive(Clone, Copy, Debug)]
+pub struct E2D2(pub f64);
+
+impl Serialize for E2D2 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        if self.0 == f64::INFINITY {
+            serializer.serialize_str(INFINITE_E2D2)
+        } else {
+            serializer.serialize_f64(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for E2D2 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Number(f64),
+            Marker(String)
+        }
+        match Option::<Stored>::deserialize(deserializer)? {
+            Some(Stored::Number(e2d2)) => Ok(E2D2(e2d2)),
+            Some(Stored::Marker(marker)) if marker == INFINITE_E2D2 => Ok(E2D2(f64::INFINITY)),
+            Some(Stored::Marker(marker)) => Err(de::Error::custom(format!("E²/D² is a number or \"{}\", got \"{}\"", INFINITE_E2D2, marker))),
+            None => Ok(E2D2(f64::INFINITY))
+        }
+    }
+}
+
+/// E²/D² fields are written as `E2D2`
+mod e2d2_format {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::E2D2;
+
+    pub fn serialize<S>(e2d2: &f64, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        E2D2(*e2d2).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error> where D: Deserializer<'de> {
+        Ok(E2D2::deserialize(deserializer)?.0)
+    }
+}
+
+fn default_t() -> usize {
+    1
+}
+
+/// Call has to demand at least one BBU
+fn check_t<E: de::Error>(t: usize) -> Result<usize, E> {
+    if t == 0 {
+        return Err(E::invalid_value(de::Unexpected::Unsigned(0), &"at least one BBU"));
+    }
+    Ok(t)
+}
+
+fn deserialize_t<'de, D>(deserializer: D) -> Result<usize, D::Error> where D: Deserializer<'de> {
+    check_t(usize::deserialize(deserializer)?)
 }
 
 impl std::fmt::Debug for StreamOfEvents {
@@ -81,6 +330,69 @@ impl PartialOrd for Class {
             return Some(Ordering::Less);
         }
 
+        if self.t > other.t {
+            return Some(Ordering::Greater);
+        }
+        if self.t < other.t {
+            return Some(Ordering::Less);
+        }
+
+        if self.sources > other.sources {
+            return Some(Ordering::Greater);
+        }
+        if self.sources < other.sources {
+            return Some(Ordering::Less);
+        }
+
+        match self.pareto_shape.partial_cmp(&other.pareto_shape)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
+        if self.priority > other.priority {
+            return Some(Ordering::Greater);
+        }
+        if self.priority < other.priority {
+            return Some(Ordering::Less);
+        }
+
+        if self.t_min > other.t_min {
+            return Some(Ordering::Greater);
+        }
+        if self.t_min < other.t_min {
+            return Some(Ordering::Less);
+        }
+
+        match self.profile.partial_cmp(&other.profile)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
+        match self.batch.partial_cmp(&other.batch)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
+        match self.arrival_map.partial_cmp(&other.arrival_map)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
+        match self.arrival_on_off.partial_cmp(&other.arrival_on_off)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
+        match self.arrival_trace.partial_cmp(&other.arrival_trace)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
+        match self.service_trace.partial_cmp(&other.service_trace)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+
         if self.eq(other) {
             return Some(Ordering::Equal);
         }
@@ -131,6 +443,17 @@ impl PartialEq for Class {
     fn eq(&self, other: &Self) -> bool {
         if self.arrival_stream_type != other.arrival_stream_type { return false; }
         if self.service_stream_type != other.service_stream_type { return false; }
+        if self.t != other.t { return false; }
+        if self.sources != other.sources { return false; }
+        if self.pareto_shape != other.pareto_shape { return false; }
+        if self.priority != other.priority { return false; }
+        if self.t_min != other.t_min { return false; }
+        if self.profile != other.profile { return false; }
+        if self.batch != other.batch { return false; }
+        if self.arrival_map != other.arrival_map { return false; }
+        if self.arrival_on_off != other.arrival_on_off { return false; }
+        if self.arrival_trace != other.arrival_trace { return false; }
+        if self.service_trace != other.service_trace { return false; }
 
         if !self.a.approx_eq(other.a, F64Margin::default()) { return false; }
         if !self.arrival_e2d2.approx_eq(other.arrival_e2d2, F64Margin::default()) { return false; }
@@ -142,6 +465,17 @@ impl PartialEq for Class {
     fn ne(&self, other: &Self) -> bool {
         self.arrival_stream_type != other.arrival_stream_type ||
         self.service_stream_type != other.service_stream_type ||
+        self.t != other.t ||
+        self.sources != other.sources ||
+        self.pareto_shape != other.pareto_shape ||
+        self.priority != other.priority ||
+        self.t_min != other.t_min ||
+        self.profile != other.profile ||
+        self.batch != other.batch ||
+        self.arrival_map != other.arrival_map ||
+        self.arrival_on_off != other.arrival_on_off ||
+        self.arrival_trace != other.arrival_trace ||
+        self.service_trace != other.service_trace ||
         !self.a.approx_eq(other.a, F64Margin::default()) ||
         !self.arrival_e2d2.approx_eq(other.arrival_e2d2, F64Margin::default()) ||
         !self.service_e2d2.approx_eq(other.service_e2d2, F64Margin::default())
@@ -154,7 +488,16 @@ impl Class {
             StreamType::Poisson => 0,
             StreamType::Uniform => 1,
             StreamType::Gamma   => 2,
-            StreamType::Pareto  => 3
+            StreamType::Pareto  => 3,
+            StreamType::Erlang  => 4,
+            StreamType::Hyperexponential => 5,
+            StreamType::Coxian  => 6,
+            StreamType::Lognormal => 7,
+            StreamType::Weibull => 8,
+            StreamType::TruncatedPareto => 9,
+            StreamType::Deterministic => 10,
+            StreamType::ShiftedExponential => 11,
+            StreamType::Trace => 12
         }
     }
 
@@ -163,69 +506,40 @@ impl Class {
             StreamType::Poisson => "Poisson",
             StreamType::Uniform => "Uniform",
             StreamType::Gamma   => "Gamma",
-            StreamType::Pareto  => "Pareto"
-        }
-    }
-
-    #[allow(dead_code)]
-    pub fn try_get_stream(str_type: StreamType, mean: f64, variance: f64) -> Option<StreamOfEvents> {
-        let new_int = 1f64 / mean;
-        return match str_type {
-            StreamType::Poisson => {
-                if relative_ne!(variance, 1f64/(new_int*new_int)) { return None; }
-
-                let distrib = Exp::new(new_int);
-                match distrib {
-                    Ok(some_distrib) => Some(StreamOfEvents::Poisson(some_distrib)),
-                    Err(e) => {
-                        println!("{}: failed to create Poisson stream with λ = {}", e, new_int);
-                        None
-                    }
-                }
-            },
-            StreamType::Uniform => {
-                let (min, max) = utils::uniform_gen_min_max(new_int, variance);
-                if min < 0f64 {
-                    println!("Wrong parameters in Uniform distribution. Minimum value is < 0");
-                    None
-                }
-                else {
-                    Some(StreamOfEvents::Uniform(Uniform::new(min, max)))
-                }
-            },
-            StreamType::Gamma => {
-                let (scale, shape) = utils::gamma_get_scale_shape(new_int, variance);
-                match Gamma::new(scale, shape) {
-                    Ok(some_distrib) => Some(StreamOfEvents::Gamma(some_distrib)),
-                    Err(e) => {
-                        println!("{}: failed to create Gamma with Ex = {} and D = {}", e, mean, variance);
-                        None
-                    }
-                }
-            },
-            StreamType::Pareto => {
-                let (scale, shape) = utils::pareto_get_scale_shape(new_int, variance);
-                match Pareto::new(scale, shape) {
-                    Ok(some_distrib) => Some(StreamOfEvents::Pareto(some_distrib)),
-                    Err(e) => {
-                        println!("{}: failed to create Pareto with Ex = {} and D = {}", e, mean, variance);
-                        None
-                    }
-                }
-            }
+            StreamType::Pareto  => "Pareto",
+            StreamType::Erlang  => "Erlang",
+            StreamType::Hyperexponential => "Hyperexponential",
+            StreamType::Coxian  => "Coxian",
+            StreamType::Lognormal => "Lognormal",
+            StreamType::Weibull => "Weibull",
+            StreamType::TruncatedPareto => "TruncatedPareto",
+            StreamType::Deterministic => "Deterministic",
+            StreamType::ShiftedExponential => "ShiftedExponential",
+            StreamType::Trace => "Trace"
         }
     }
 
     pub fn new(new_stream_type: StreamType, end_stream_type: StreamType,
                new_int: f64, new_e2_d2: f64,
-               end_int: f64, end_e2_d2: f64 ) -> Self {
+               end_int: f64, end_e2_d2: f64, t: usize) -> Self {
         Class {
             a: new_int / end_int,
             mu: end_int,
             arrival_stream_type: new_stream_type,
             arrival_e2d2: new_e2_d2,
             service_stream_type: end_stream_type,
-            service_e2d2: end_e2_d2
+            service_e2d2: end_e2_d2,
+            t: t,
+            sources: None,
+            pareto_shape: None,
+            priority: 0,
+            t_min: None,
+            profile: None,
+            batch: None,
+            arrival_map: None,
+            arrival_on_off: None,
+            arrival_trace: None,
+            service_trace: None
         }
     }
 
@@ -233,7 +547,7 @@ impl Class {
         self.a
     }
 
-    pub fn get_new_intensity(&self) -> f64 { self.a/self.mu }
+    pub fn get_new_intensity(&self) -> f64 { self.a*self.mu }
 
     pub fn get_end_intensity(&self) -> f64 { self.mu }
 
@@ -261,6 +575,99 @@ impl Class {
         self.service_e2d2
     }
 
+    pub fn get_t(&self) -> usize {
+        self.t
+    }
+
+    pub fn get_sources(&self) -> Option<usize> {
+        self.sources
+    }
+
+    /// Sets number of traffic sources. Offered traffic `a` and arrival stream describe then single idle source.
+    pub fn set_sources(&mut self, sources: Option<usize>) {
+        self.sources = sources;
+    }
+
+    pub fn get_priority(&self) -> u32 {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: u32) {
+        self.priority = priority;
+    }
+
+    pub fn get_pareto_shape(&self) -> f64 {
+        self.pareto_shape.unwrap_or(utils::TRUNCATED_PARETO_SHAPE)
+    }
+
+    pub fn set_pareto_shape(&mut self, pareto_shape: Option<f64>) {
+        self.pareto_shape = pareto_shape;
+    }
+
+    pub fn get_t_min(&self) -> Option<usize> {
+        self.t_min
+    }
+
+    pub fn set_t_min(&mut self, t_min: Option<usize>) {
+        self.t_min = t_min;
+    }
+
+    pub fn get_profile(&self) -> Option<&IntensityProfile> {
+        self.profile.as_ref()
+    }
+
+    pub fn set_profile(&mut self, profile: Option<IntensityProfile>) {
+        self.profile = profile;
+    }
+
+    pub fn get_batch(&self) -> Option<&BatchDescription> {
+        self.batch.as_ref()
+    }
+
+    pub fn set_batch(&mut self, batch: Option<BatchDescription>) {
+        self.batch = batch;
+    }
+
+    pub fn get_arrival_map(&self) -> Option<&MarkovArrivalDescription> {
+        self.arrival_map.as_ref()
+    }
+
+    pub fn set_arrival_map(&mut self, arrival_map: Option<MarkovArrivalDescription>) {
+        self.arrival_map = arrival_map;
+    }
+
+    pub fn get_arrival_on_off(&self) -> Option<&OnOffDescription> {
+        self.arrival_on_off.as_ref()
+    }
+
+    pub fn set_arrival_on_off(&mut self, arrival_on_off: Option<OnOffDescription>) {
+        self.arrival_on_off = arrival_on_off;
+    }
+
+    pub fn get_arrival_trace(&self) -> Option<&TraceDescription> {
+        self.arrival_trace.as_ref()
+    }
+
+    /// Arrival E²/D² of the class becomes the empirical E²/D² of the trace
+    pub fn set_arrival_trace(&mut self, arrival_trace: Option<TraceDescription>) {
+        if let Some(trace) = &arrival_trace {
+            self.arrival_e2d2 = trace.e2d2;
+        }
+        self.arrival_trace = arrival_trace;
+    }
+
+    pub fn get_service_trace(&self) -> Option<&TraceDescription> {
+        self.service_trace.as_ref()
+    }
+
+    /// Service E²/D² of the class becomes the empirical E²/D² of the trace
+    pub fn set_service_trace(&mut self, service_trace: Option<TraceDescription>) {
+        if let Some(trace) = &service_trace {
+            self.service_e2d2 = trace.e2d2;
+        }
+        self.service_trace = service_trace;
+    }
+
 }
 
 impl FromStr for StreamType {
@@ -271,6 +678,15 @@ impl FromStr for StreamType {
             "uniform" => Ok(StreamType::Uniform),
             "gamma"   => Ok(StreamType::Gamma),
             "pareto"  => Ok(StreamType::Pareto),
+            "erlang"  => Ok(StreamType::Erlang),
+            "hyperexponential" => Ok(StreamType::Hyperexponential),
+            "coxian"  => Ok(StreamType::Coxian),
+            "lognormal" => Ok(StreamType::Lognormal),
+            "weibull" => Ok(StreamType::Weibull),
+            "truncated_pareto" => Ok(StreamType::TruncatedPareto),
+            "deterministic" => Ok(StreamType::Deterministic),
+            "shifted_exponential" => Ok(StreamType::ShiftedExponential),
+            "trace" => Ok(StreamType::Trace),
             _         => Err(()),
         }
     }
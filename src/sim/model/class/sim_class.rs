@@ -1,51 +1,132 @@
 use std::cmp::Ordering;
 use rand::prelude::Distribution;
-use rand_distr::{Exp, Uniform, Gamma, Pareto};
+use rand_distr::{Exp, Uniform, Gamma, Pareto, LogNormal, Weibull};
 use rand::Rng;
-use approx::relative_ne;
 use std::fmt;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{self, MapAccess, SeqAccess, Visitor};
 use serde::ser::SerializeStruct;
 use crate::sim::model::class;
 
-use crate::sim::model::class::{Class, StreamType, StreamOfEvents};
+use crate::sim::model::class::{Class, E2D2, StreamType, StreamOfEvents};
 use crate::sim::model::class::utils;
+use crate::sim::model::markov::MarkovArrivalDescription;
+use crate::sim::model::onoff::OnOffSource;
+use crate::sim::model::trace::TraceDescription;
 
-#[derive(Clone, Copy)]
+/// State of the arrival process of the class that is kept between arrivals
+#[derive(Clone, Default)]
+pub struct ArrivalState {
+    /// Phase of Markovian arrival process
+    pub phase: usize,
+    /// States of ON/OFF sources, empty before the first arrival
+    pub on_off: Vec<OnOffSource>,
+    /// Position in the replayed arrival trace, None before the first arrival
+    pub trace: Option<usize>
+}
+
+#[derive(Clone)]
 pub struct SimClass {
     pub tr_class: Class,
     arrival_stream   : StreamOfEvents,
     service_stream   : StreamOfEvents,
+    /// Markovian arrival process scaled to the arrival intensity, it replaces the arrival stream
+    arrival_map      : Option<MarkovArrivalDescription>,
 }
 
 impl SimClass {
     pub fn new(new_stream_type: StreamType, end_stream_type: StreamType,
                new_int: f64, new_e2_d2: f64,
-               end_int: f64, end_e2_d2: f64 ) -> Option<Self> {
+               end_int: f64, end_e2_d2: f64, t: usize) -> Option<Self> {
         let (arrival_mean, arrival_variance) = utils::get_e_d(new_int, new_e2_d2);
         let (service_mean, service_variance) = utils::get_e_d(end_int, end_e2_d2);
 
-        let arrival_str_opt = Self::try_get_stream(new_stream_type, arrival_mean, arrival_variance);
-        let service_str_opt = Self::try_get_stream(end_stream_type, service_mean, service_variance);
+        let arrival_str_opt = Self::try_get_stream(new_stream_type, arrival_mean, arrival_variance, utils::TRUNCATED_PARETO_SHAPE);
+        let service_str_opt = Self::try_get_stream(end_stream_type, service_mean, service_variance, utils::TRUNCATED_PARETO_SHAPE);
 
         match (arrival_str_opt, service_str_opt) {
             (Some(arrival_str), Some(service_str)) =>
                 Some (SimClass {
-                    tr_class: Class::new(new_stream_type, end_stream_type, new_int, new_e2_d2, end_int, end_e2_d2),
+                    tr_class: Class::new(new_stream_type, end_stream_type, new_int, new_e2_d2, end_int, end_e2_d2, t),
                     arrival_stream: arrival_str,
                     service_stream: service_str,
+                    arrival_map: None,
                 }),
             _ => None
         }
     }
 
-    pub fn try_get_stream(str_type: StreamType, mean: f64, variance: f64) -> Option<StreamOfEvents> {
+    /// Prepares streams of the class described by its parameters (e.g. read from the configuration file)
+    pub fn from_class(tr_class: &Class) -> Option<Self> {
+        if let Some(profile) = tr_class.get_profile() {
+            if !profile.is_valid() {
+                println!("Wrong intensity profile {:?}", profile);
+                return None;
+            }
+        }
+        let arrival_map = match tr_class.get_arrival_map() {
+            Some(_) if tr_class.get_sources().is_some() => {
+                println!("Markovian arrival process can't be used with finite number of sources");
+                return None;
+            }
+            Some(arrival_map) if !arrival_map.is_valid() => {
+                println!("Wrong Markovian arrival process {:?}", arrival_map);
+                return None;
+            }
+            Some(arrival_map) => arrival_map.scaled(tr_class.get_new_intensity()),
+            None => None
+        };
+        if let Some(on_off) = tr_class.get_arrival_on_off() {
+            if !on_off.is_valid() || tr_class.get_sources().is_some() || arrival_map.is_some() {
+                println!("Wrong ON/OFF sources {:?}, they replace arrival stream of infinite number of sources", on_off);
+                return None;
+            }
+        }
+        if let Some(batch) = tr_class.get_batch() {
+            if !batch.size.is_valid() {
+                println!("Wrong batch size {:?}", batch.size);
+                return None;
+            }
+        }
+        let (arrival_mean, arrival_variance) = utils::get_e_d(tr_class.get_new_intensity(), tr_class.arrival_e2d2);
+        let (service_mean, service_variance) = utils::get_e_d(tr_class.mu, tr_class.service_e2d2);
+
+        let pareto_shape = tr_class.get_pareto_shape();
+        match (Self::try_get_class_stream(tr_class.arrival_stream_type, tr_class.get_arrival_trace(), arrival_mean, arrival_variance, pareto_shape),
+               Self::try_get_class_stream(tr_class.service_stream_type, tr_class.get_service_trace(), service_mean, service_variance, pareto_shape)) {
+            (Some(arrival_str), Some(service_str)) =>
+                Some(SimClass { tr_class: tr_class.clone(), arrival_stream: arrival_str, service_stream: service_str, arrival_map: arrival_map }),
+            _ => None
+        }
+    }
+
+    /// Prepares the stream of the class, trace stream is scaled to `mean`
+    fn try_get_class_stream(str_type: StreamType, trace: Option<&TraceDescription>, mean: f64, variance: f64, pareto_shape: f64) -> Option<StreamOfEvents> {
+        match (str_type, trace) {
+            (StreamType::Trace, Some(trace)) => match trace.stream(mean) {
+                Ok(stream) => Some(StreamOfEvents::Trace(stream)),
+                Err(e) => {
+                    println!("Failed to read trace {}", e);
+                    None
+                }
+            },
+            (StreamType::Trace, None) | (_, Some(_)) => {
+                println!("Trace is used only by trace stream and trace stream needs recorded times");
+                None
+            }
+            (_, None) => Self::try_get_stream(str_type, mean, variance, pareto_shape)
+        }
+    }
+
+    /// Stream with given mean and variance, `pareto_shape` is used only by truncated Pareto stream
+    pub fn try_get_stream(str_type: StreamType, mean: f64, variance: f64, pareto_shape: f64) -> Option<StreamOfEvents> {
         let new_int = 1f64 / mean;
+        if let Err(reason) = str_type.check_e2d2(mean * mean / variance) {
+            println!("{}", reason);
+            return None;
+        }
         return match str_type {
             StreamType::Poisson => {
-                if relative_ne!(variance, 1f64/(new_int*new_int)) { return None; }
-
                 let distrib = Exp::new(new_int);
                 match distrib {
                     Ok(some_distrib) => Some(StreamOfEvents::Poisson(some_distrib)),
@@ -76,7 +157,13 @@ impl SimClass {
                 }
             },
             StreamType::Pareto => {
-                let (scale, shape) = utils::pareto_get_scale_shape(new_int, variance);
+                let (scale, shape) = match utils::pareto_get_scale_shape(new_int, variance) {
+                    Some(params) => params,
+                    None => {
+                        println!("Wrong parameters in Pareto distribution. Variance is not finite");
+                        return None;
+                    }
+                };
                 match Pareto::new(scale, shape) {
                     Ok(some_distrib) => Some(StreamOfEvents::Pareto(some_distrib)),
                     Err(e) => {
@@ -84,30 +171,115 @@ impl SimClass {
                         None
                     }
                 }
+            },
+            StreamType::Erlang => {
+                let (phases, p, rate) = utils::erlang_get_phases_probability_rate(new_int, variance);
+                match Exp::new(rate) {
+                    Ok(phase) => Some(StreamOfEvents::Erlang { phases: phases, p: p, phase: phase }),
+                    Err(e) => {
+                        println!("{}: failed to create Erlang with Ex = {} and D = {}", e, mean, variance);
+                        None
+                    }
+                }
+            },
+            StreamType::Hyperexponential => {
+                let (p, rate1, rate2) = utils::hyperexponential_get_probability_rates(new_int, variance);
+                match (Exp::new(rate1), Exp::new(rate2)) {
+                    (Ok(phase1), Ok(phase2)) => Some(StreamOfEvents::Hyperexponential { p: p, phases: [phase1, phase2] }),
+                    _ => {
+                        println!("Failed to create hyperexponential with Ex = {} and D = {}", mean, variance);
+                        None
+                    }
+                }
+            },
+            StreamType::Coxian => {
+                let (rate1, rate2, p) = utils::coxian_get_rates_probability(new_int, variance);
+                match (Exp::new(rate1), Exp::new(rate2)) {
+                    (Ok(phase1), Ok(phase2)) => Some(StreamOfEvents::Coxian { p: p, phases: [phase1, phase2] }),
+                    _ => {
+                        println!("Failed to create Coxian with Ex = {} and D = {}", mean, variance);
+                        None
+                    }
+                }
+            },
+            StreamType::Lognormal => {
+                let (location, scale) = utils::lognormal_get_location_scale(new_int, variance);
+                match LogNormal::new(location, scale) {
+                    Ok(some_distrib) => Some(StreamOfEvents::Lognormal(some_distrib)),
+                    Err(e) => {
+                        println!("{}: failed to create Lognormal with Ex = {} and D = {}", e, mean, variance);
+                        None
+                    }
+                }
+            },
+            StreamType::Weibull => {
+                let (scale, shape) = utils::weibull_get_scale_shape(new_int, variance);
+                match Weibull::new(scale, shape) {
+                    Ok(some_distrib) => Some(StreamOfEvents::Weibull(some_distrib)),
+                    Err(e) => {
+                        println!("{}: failed to create Weibull with Ex = {} and D = {}", e, mean, variance);
+                        None
+                    }
+                }
+            },
+            StreamType::TruncatedPareto => {
+                if !utils::is_valid_truncated_pareto_shape(pareto_shape) {
+                    println!("Wrong truncated Pareto shape {}, it has to be in range 0..2 except 1", pareto_shape);
+                    return None;
+                }
+                let (low, high) = utils::truncated_pareto_get_bounds(new_int, variance, pareto_shape);
+                if !(low > 0f64 && high.is_finite()) {
+                    println!("Failed to create truncated Pareto with Ex = {} and D = {}", mean, variance);
+                    return None;
+                }
+                Some(StreamOfEvents::TruncatedPareto { low: low, high: high, shape: pareto_shape })
+            },
+            StreamType::Deterministic => Some(StreamOfEvents::Deterministic(mean)),
+            StreamType::ShiftedExponential => {
+                let (shift, rate) = utils::shifted_exponential_get_shift_rate(new_int, variance);
+                match Exp::new(rate) {
+                    Ok(phase) => Some(StreamOfEvents::ShiftedExponential { shift: shift, phase: phase }),
+                    Err(e) => {
+                        println!("{}: failed to create shifted exponential with Ex = {} and D = {}", e, mean, variance);
+                        None
+                    }
+                }
+            },
+            StreamType::Trace => {
+                println!("Trace stream needs recorded times, it can't be described by Ex = {} and D = {}", mean, variance);
+                None
             }
         }
     }
 
     pub fn get_time_new_call<R: Rng + ?Sized>(&self,rng: &mut R) -> f64 {
-        match &self.arrival_stream {
-            StreamOfEvents::Poisson(distr)=> distr.sample(rng),
-            StreamOfEvents::Uniform(distr)=> distr.sample(rng),
-            StreamOfEvents::Gamma(distr)=> distr.sample(rng),
-            StreamOfEvents::Pareto(distr)=> distr.sample(rng),
-            //_ => panic!("Not supported distraibution {:?}", self.arrival_stream)
-        }
+        self.arrival_stream.sample(rng)
     }
 
-    pub fn get_time_end_call<R: Rng + ?Sized>(&self,rng: &mut R) -> f64 {
-        match &self.service_stream {
-            StreamOfEvents::Poisson(distr)=> distr.sample(rng),
-            StreamOfEvents::Uniform(distr)=> distr.sample(rng),
-            StreamOfEvents::Gamma(distr)=> distr.sample(rng),
-            StreamOfEvents::Pareto(distr)=> distr.sample(rng),
-            //_ => panic!("Not supported distraibution {:?}", self.service_stream)
+    /// Time to the next arrival after simulation time `time`, stretched by the intensity profile of the class.
+    /// `state` of non-renewal arrival process is kept by the caller between arrivals.
+    pub fn get_time_new_call_at<R: Rng + ?Sized>(&self, rng: &mut R, time: f64, state: &mut ArrivalState) -> f64 {
+        let operational_time = match (&self.arrival_map, self.tr_class.get_arrival_on_off()) {
+            (Some(arrival_map), _) => arrival_map.sample(rng, &mut state.phase),
+            (None, Some(on_off)) => on_off.sample(rng, self.tr_class.get_new_intensity(), &mut state.on_off),
+            (None, None) => self.arrival_stream.sample_at(rng, &mut state.trace)
+        };
+        match self.tr_class.get_profile() {
+            Some(profile) => profile.scale(time, operational_time),
+            None => operational_time
         }
     }
 
+    /// Number of calls that arrive at once, 1 if the class has no batch arrivals
+    pub fn get_batch_size<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        self.tr_class.get_batch().map_or(1, |batch| batch.size.sample(rng))
+    }
+
+    /// Service time of the call, `position` of the replayed service trace is kept by the caller
+    pub fn get_time_end_call<R: Rng + ?Sized>(&self, rng: &mut R, position: &mut Option<usize>) -> f64 {
+        self.service_stream.sample_at(rng, position)
+    }
+
     #[allow(dead_code)]
     pub fn get_a(&self) -> f64 {
         self.tr_class.a
@@ -142,16 +314,33 @@ impl SimClass {
     pub fn get_end_e2d2(&self) -> f64 {
         self.tr_class.service_e2d2
     }
+
+    pub fn get_t(&self) -> usize {
+        self.tr_class.t
+    }
+
+    pub fn get_sources(&self) -> Option<usize> {
+        self.tr_class.sources
+    }
+
+    pub fn get_priority(&self) -> u32 {
+        self.tr_class.priority
+    }
+
+    pub fn get_t_min(&self) -> Option<usize> {
+        self.tr_class.t_min
+    }
 }
 
 impl Serialize for SimClass {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        let mut state = serializer.serialize_struct("TrClass", 5)?;
+        let mut state = serializer.serialize_struct("TrClass", 6)?;
         state.serialize_field("a", &self.tr_class.a)?;
         state.serialize_field("arrival_str_type", &self.tr_class.arrival_stream_type)?;
-        state.serialize_field("arrival_e2d2", &self.tr_class.arrival_e2d2)?;
+        state.serialize_field("arrival_e2d2", &E2D2(self.tr_class.arrival_e2d2))?;
         state.serialize_field("service_stream_type", &self.tr_class.service_stream_type)?;
-        state.serialize_field("service_e2d2", &self.tr_class.service_e2d2)?;
+        state.serialize_field("service_e2d2", &E2D2(self.tr_class.service_e2d2))?;
+        state.serialize_field("t", &self.tr_class.t)?;
         state.end()
     }
 }
@@ -161,7 +350,7 @@ impl<'de> Deserialize<'de> for SimClass {
         where
             D: Deserializer<'de>,
     {
-        enum Field { A, NewStreamType, EndStreamType, NewE2D2, EndE2D2 }
+        enum Field { A, NewStreamType, EndStreamType, NewE2D2, EndE2D2, T }
         // This part could also be generated independently by:
         //
         //    #[derive(Deserialize)]
@@ -178,7 +367,7 @@ impl<'de> Deserialize<'de> for SimClass {
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("`a`, `New_stream_type`, `End_stream_type`, `New_e2_d2`, `End_e2_d2`")
+                        formatter.write_str("`a`, `New_stream_type`, `End_stream_type`, `New_e2_d2`, `End_e2_d2`, `t`")
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -191,6 +380,7 @@ impl<'de> Deserialize<'de> for SimClass {
                             "End_stream_type" => Ok(Field::EndStreamType),
                             "New_e2_d2" => Ok(Field::NewE2D2),
                             "End_e2_d2" => Ok(Field::EndE2D2),
+                            "t" => Ok(Field::T),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -216,14 +406,15 @@ impl<'de> Deserialize<'de> for SimClass {
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
                 let arrival_stream_type = seq.next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                let arrival_e2d2 = seq.next_element()?
+                let E2D2(arrival_e2d2) = seq.next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
                 let service_stream_type = seq.next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                let service_e2d2 = seq.next_element()?
+                let E2D2(service_e2d2) = seq.next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let t = super::check_t(seq.next_element()?.unwrap_or(1))?;
 
-                Ok(SimClass::new(arrival_stream_type, service_stream_type, a, arrival_e2d2, 1.0, service_e2d2).
+                Ok(SimClass::new(arrival_stream_type, service_stream_type, a, arrival_e2d2, 1.0, service_e2d2, t).
                     ok_or_else(|| de::Error::custom(format_args!("Failed to create object"))))?
             }
 
@@ -236,6 +427,7 @@ impl<'de> Deserialize<'de> for SimClass {
                 let mut end_stream_type = None;
                 let mut new_e2_d2 = None;
                 let mut end_e2_d2 = None;
+                let mut t = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -261,13 +453,19 @@ impl<'de> Deserialize<'de> for SimClass {
                             if new_e2_d2.is_some() {
                                 return Err(de::Error::duplicate_field("new_e2_d2"));
                             }
-                            new_e2_d2 = Some(map.next_value()?);
+                            new_e2_d2 = Some(map.next_value::<E2D2>()?.0);
                         }
                         Field::EndE2D2 => {
                             if end_e2_d2.is_some() {
                                 return Err(de::Error::duplicate_field("end_e2_d2"));
                             }
-                            end_e2_d2 = Some(map.next_value()?);
+                            end_e2_d2 = Some(map.next_value::<E2D2>()?.0);
+                        }
+                        Field::T => {
+                            if t.is_some() {
+                                return Err(de::Error::duplicate_field("t"));
+                            }
+                            t = Some(map.next_value()?);
                         }
                     }
                 }
@@ -276,13 +474,14 @@ impl<'de> Deserialize<'de> for SimClass {
                 let end_stream_type: StreamType = end_stream_type.ok_or_else(|| de::Error::missing_field("end_stream_type"))?;
                 let new_e2_d2: f64 = new_e2_d2.ok_or_else(|| de::Error::missing_field("new_e2_d2"))?;
                 let end_e2_d2: f64 = end_e2_d2.ok_or_else(|| de::Error::missing_field("end_e2_d2"))?;
+                let t: usize = super::check_t(t.unwrap_or(1))?;
 
-                Ok(SimClass::new(new_stream_type, end_stream_type, a, new_e2_d2, 1.0, end_e2_d2).
+                Ok(SimClass::new(new_stream_type, end_stream_type, a, new_e2_d2, 1.0, end_e2_d2, t).
                     ok_or_else(|| de::Error::custom(format_args!("Failed to create object"))))?
             }
         }
 
-        const FIELDS: &'static [&'static str] = &["a", "new_stream_type", "end_stream_type", "new_e2_d2", "end_e2_d2"];
+        const FIELDS: &'static [&'static str] = &["a", "new_stream_type", "end_stream_type", "new_e2_d2", "end_e2_d2", "t"];
         deserializer.deserialize_struct("SimClass", FIELDS, ClassVisitor)
     }
 }
@@ -1,5 +1,3 @@
-use assert_approx_eq::assert_approx_eq;
-
 pub fn uniform_gen_min_max(intensity: f64, variance: f64) -> (f64, f64) {
     let e_val = 1f64/intensity;
 
@@ -29,7 +27,8 @@ pub fn gamma_get_scale_shape(intensity: f64, variance: f64) -> (f64, f64) {
     (scale, shape)
 }
 
-pub fn pareto_get_scale_shape(intensity: f64, variance: f64) -> (f64, f64) {
+/// Returns None if the shape doesn't give finite variance
+pub fn pareto_get_scale_shape(intensity: f64, variance: f64) -> Option<(f64, f64)> {
 
     //https://en.wikipedia.org/wiki/Pareto_distribution
     //shape = \alpha
@@ -42,14 +41,141 @@ pub fn pareto_get_scale_shape(intensity: f64, variance: f64) -> (f64, f64) {
     let alpha = 1f64 + tmp;
     let x_m = par_e * (alpha - 1f64)/alpha;
 
+    if !(alpha > 2f64 && x_m > 0f64) {
+        return None;
+    }
     let chck_e = alpha * x_m  / (alpha - 1f64);
     let chck_d = x_m*x_m*alpha/((alpha-1f64)*(alpha-1f64)*(alpha-2f64));
 
-    assert_approx_eq!(chck_e, par_e, 0.001f64);
-    assert_approx_eq!(chck_d, variance, 0.001f64);
+    if (chck_e / par_e - 1f64).abs() > 0.001f64 || (chck_d / variance - 1f64).abs() > 0.001f64 {
+        return None;
+    }
+    Some((x_m, alpha))
+}
+
+/// Returns number of phases k, probability p and rate of every phase of the mixed Erlang distribution.
+/// With probability p the sum of k-1 phases is taken, otherwise the sum of k phases (Tijms two-moment fit).
+/// Squared coefficient of variation has to be in range 1/k..1/(k-1), pure Erlang-k has p = 0.
+pub fn erlang_get_phases_probability_rate(intensity: f64, variance: f64) -> (usize, f64, f64) {
+    let par_e = 1f64/intensity;
+    let scv = variance / (par_e * par_e);
+
+    let k = (1f64 / scv).ceil().max(1f64);
+    let p = ((k * scv - f64::sqrt(k * (1f64 + scv) - k * k * scv)) / (1f64 + scv)).max(0f64);
+    let rate = (k - p) / par_e;
+
+    (k as usize, p, rate)
+}
+
+/// Returns probability p1 and rates of two phases of the hyperexponential distribution with balanced means,
+/// p1/rate1 = p2/rate2. Squared coefficient of variation has to be at least 1.
+pub fn hyperexponential_get_probability_rates(intensity: f64, variance: f64) -> (f64, f64, f64) {
+    let par_e = 1f64/intensity;
+    let scv = variance / (par_e * par_e);
+
+    let p1 = 0.5f64 * (1f64 + f64::sqrt(((scv - 1f64) / (scv + 1f64)).max(0f64)));
+    let rate1 = 2f64 * p1 / par_e;
+    let rate2 = 2f64 * (1f64 - p1) / par_e;
+
+    (p1, rate1, rate2)
+}
+
+/// Returns rates of two phases and probability p that the second phase follows the first one in
+/// Coxian-2 distribution. Squared coefficient of variation has to be at least 0.5.
+pub fn coxian_get_rates_probability(intensity: f64, variance: f64) -> (f64, f64, f64) {
+    let par_e = 1f64/intensity;
+    let scv = variance / (par_e * par_e);
+
+    let rate1 = 2f64 / par_e;
+    let p = (0.5f64 / scv).min(1f64);
+    let rate2 = 1f64 / (par_e * scv);
+
+    (rate1, rate2, p)
+}
 
-    assert!(alpha > 2f64);
-    assert!(x_m > 0f64);
+/// Logarithm of the gamma function (Lanczos approximation), x > 0
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+        771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905, -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7];
+    if x < 0.5f64 {
+        // Reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1f64 - x);
+    }
+    let x = x - 1f64;
+    let t = x + 7.5f64;
+    let sum = COEFFICIENTS[1..].iter().enumerate()
+        .fold(COEFFICIENTS[0], |sum, (idx, c)| sum + c / (x + idx as f64 + 1f64));
+    0.5f64 * (2f64 * std::f64::consts::PI).ln() + (x + 0.5f64) * t.ln() - t + sum.ln()
+}
+
+/// Finds the root of increasing function in range min..max by bisection
+fn bisection(function: impl Fn(f64) -> f64, mut min: f64, mut max: f64) -> f64 {
+    for _ in 0..200 {
+        let middle = 0.5f64 * (min + max);
+        if function(middle) < 0f64 { min = middle; } else { max = middle; }
+    }
+    0.5f64 * (min + max)
+}
+
+/// Returns location μ and scale σ of the logarithm of Lognormal distribution
+pub fn lognormal_get_location_scale(intensity: f64, variance: f64) -> (f64, f64) {
+    let par_e = 1f64/intensity;
+    let sigma2 = (1f64 + variance / (par_e * par_e)).ln();
 
-    (x_m, alpha)
-}
\ No newline at end of file
+    (par_e.ln() - 0.5f64 * sigma2, sigma2.sqrt())
+}
+
+/// Returns scale and shape of Weibull distribution. Shape solves Γ(1+2/k) / Γ(1+1/k)² = 1 + D/E²
+pub fn weibull_get_scale_shape(intensity: f64, variance: f64) -> (f64, f64) {
+    let par_e = 1f64/intensity;
+    let target = (1f64 + variance / (par_e * par_e)).ln();
+
+    // The ratio decreases with the shape, so the function of logarithm of 1/k increases
+    let ln_ratio = |ln_inv_shape: f64| {
+        let inv_shape = ln_inv_shape.exp();
+        ln_gamma(1f64 + 2f64 * inv_shape) - 2f64 * ln_gamma(1f64 + inv_shape) - target
+    };
+    let shape = 1f64 / bisection(ln_ratio, (1e-3f64).ln(), (50f64).ln()).exp();
+    let scale = par_e / ln_gamma(1f64 + 1f64 / shape).exp();
+
+    (scale, shape)
+}
+
+/// Default shape of truncated Pareto distribution, the tail is heavier than in Pareto distribution with finite variance
+pub const TRUNCATED_PARETO_SHAPE: f64 = 1.5f64;
+
+/// Truncated Pareto distribution can have any E²/D² with the shape in range 0..2, except 1
+pub fn is_valid_truncated_pareto_shape(shape: f64) -> bool {
+    shape > 0f64 && shape < 2f64 && shape != 1f64
+}
+
+/// n-th moment of truncated Pareto distribution with the shape `alpha` on range low..high
+fn truncated_pareto_moment(low: f64, high: f64, alpha: f64, n: f64) -> f64 {
+    alpha * low.powf(alpha) / (1f64 - (low / high).powf(alpha)) * (high.powf(n - alpha) - low.powf(n - alpha)) / (n - alpha)
+}
+
+/// Returns lower and upper bound of truncated Pareto distribution with given shape.
+/// Squared coefficient of variation depends only on the ratio of bounds.
+pub fn truncated_pareto_get_bounds(intensity: f64, variance: f64, shape: f64) -> (f64, f64) {
+    let par_e = 1f64/intensity;
+    let scv = variance / (par_e * par_e);
+
+    let scv_of_ratio = |ln_ratio: f64| {
+        let high = ln_ratio.exp();
+        let e = truncated_pareto_moment(1f64, high, shape, 1f64);
+        truncated_pareto_moment(1f64, high, shape, 2f64) / (e * e) - 1f64 - scv
+    };
+    let ratio = bisection(scv_of_ratio, 1e-6f64, 100f64).exp();
+    let low = par_e / truncated_pareto_moment(1f64, ratio, shape, 1f64);
+
+    (low, low * ratio)
+}
+
+/// Returns the constant shift and the rate of exponential part. Squared coefficient of variation has to be at most 1.
+pub fn shifted_exponential_get_shift_rate(intensity: f64, variance: f64) -> (f64, f64) {
+    let par_e = 1f64/intensity;
+    let std_dev = variance.sqrt();
+
+    ((par_e - std_dev).max(0f64), 1f64 / std_dev)
+}
@@ -0,0 +1,152 @@
+use std::str::FromStr;
+use rand::Rng;
+use rand::prelude::Distribution;
+use rand_distr::Exp;
+use serde::{Deserialize, Serialize};
+
+/// Markovian arrival process. Its inter-arrival times are correlated, the process keeps its phase
+/// between subsequent arrivals. Matrices describe the shape of the stream, it is scaled to the
+/// arrival intensity of the class.
+#[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Debug)]
+pub enum MarkovArrivalDescription {
+    /// Markov-modulated Poisson process. Generator of the modulating chain and Poisson arrival
+    /// intensity in every phase
+    Mmpp { generator: Vec<Vec<f64>>, rates: Vec<f64> },
+    /// General MAP. D0 describes transitions without arrival, D1 transitions with arrival
+    Map { d0: Vec<Vec<f64>>, d1: Vec<Vec<f64>> }
+}
+
+impl MarkovArrivalDescription {
+    /// Matrices D0 and D1 of the process
+    pub fn matrices(&self) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        match self {
+            MarkovArrivalDescription::Mmpp { generator, rates } => {
+                let mut d0 = generator.clone();
+                let mut d1 = vec![vec![0f64; rates.len()]; rates.len()];
+                for (phase, rate) in rates.iter().enumerate() {
+                    d0[phase][phase] -= rate;
+                    d1[phase][phase] = *rate;
+                }
+                (d0, d1)
+            }
+            MarkovArrivalDescription::Map { d0, d1 } => (d0.clone(), d1.clone())
+        }
+    }
+
+    /// Checks if D0 + D1 is a generator and the process leaves every phase
+    pub fn is_valid(&self) -> bool {
+        if let MarkovArrivalDescription::Mmpp { generator, rates } = self {
+            if generator.len() != rates.len() {
+                return false;
+            }
+        }
+        let (d0, d1) = self.matrices();
+        let n = d0.len();
+        n > 0 && d1.len() == n
+            && d0.iter().chain(d1.iter()).all(|row| row.len() == n)
+            && d1.iter().flatten().all(|x| *x >= 0f64)
+            && (0..n).all(|i| {
+                let row_sum: f64 = d0[i].iter().chain(d1[i].iter()).sum();
+                d0[i][i] < 0f64 && (0..n).all(|j| i == j || d0[i][j] >= 0f64)
+                    && row_sum.abs() <= 1e-9 * d0[i][i].abs()
+            })
+            && self.rate().is_some()
+    }
+
+    /// Stationary distribution of the phase, None if the chain is not irreducible
+    fn stationary(&self) -> Option<Vec<f64>> {
+        let (d0, d1) = self.matrices();
+        let n = d0.len();
+        // pi (D0 + D1) = 0, the last equation is replaced by normalization
+        let mut a: Vec<Vec<f64>> = (0..n).map(|col| (0..n).map(|row| d0[row][col] + d1[row][col]).collect()).collect();
+        let mut b = vec![0f64; n];
+        a[n - 1] = vec![1f64; n];
+        b[n - 1] = 1f64;
+
+        for col in 0..n {
+            let pivot = (col..n).max_by(|x, y| a[*x][col].abs().total_cmp(&a[*y][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            b.swap(col, pivot);
+            for row in 0..n {
+                if row != col {
+                    let factor = a[row][col] / a[col][col];
+                    for k in col..n {
+                        a[row][k] -= factor * a[col][k];
+                    }
+                    b[row] -= factor * b[col];
+                }
+            }
+        }
+        Some((0..n).map(|i| b[i] / a[i][i]).collect())
+    }
+
+    /// Mean arrival intensity, None if the chain is not irreducible
+    pub fn rate(&self) -> Option<f64> {
+        let (_, d1) = self.matrices();
+        let pi = self.stationary()?;
+        Some(pi.iter().zip(d1.iter()).map(|(p, row)| p * row.iter().sum::<f64>()).sum())
+    }
+
+    /// The same process with time scaled so that its mean arrival intensity is `intensity`
+    pub fn scaled(&self, intensity: f64) -> Option<Self> {
+        let factor = intensity / self.rate()?;
+        let (d0, d1) = self.matrices();
+        let scale = |matrix: Vec<Vec<f64>>| matrix.into_iter()
+            .map(|row| row.into_iter().map(|x| x * factor).collect())
+            .collect();
+        Some(MarkovArrivalDescription::Map { d0: scale(d0), d1: scale(d1) })
+    }
+
+    /// Time to the next arrival. Phase is updated to the one after the arrival.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, phase: &mut usize) -> f64 {
+        let (d0, d1) = match self {
+            MarkovArrivalDescription::Map { d0, d1 } => (d0, d1),
+            MarkovArrivalDescription::Mmpp { .. } => panic!("MMPP has to be scaled before sampling")
+        };
+        let mut time = 0f64;
+        loop {
+            let i = *phase;
+            let total = -d0[i][i];
+            time += Exp::new(total).expect("Wrong MAP matrix D0").sample(rng);
+
+            let mut choice = rng.gen::<f64>() * total;
+            for (j, rate) in d1[i].iter().enumerate() {
+                if choice < *rate {
+                    *phase = j;
+                    return time;
+                }
+                choice -= rate;
+            }
+            // Transition without arrival
+            *phase = (0..d0.len()).filter(|j| *j != i)
+                .find(|j| {
+                    let found = choice < d0[i][*j];
+                    choice -= d0[i][*j];
+                    found
+                })
+                .unwrap_or(i);
+        }
+    }
+}
+
+/// Two-phase MMPP, format: rate1,rate2,switch12,switch21 (intensities of arrivals in phases and
+/// of switching between phases)
+impl FromStr for MarkovArrivalDescription {
+    type Err = ();
+    fn from_str(input: &str) -> Result<MarkovArrivalDescription, Self::Err> {
+        let params = input.split(',')
+            .map(|x| f64::from_str(x.trim()).map_err(|_| ()))
+            .collect::<Result<Vec<f64>, ()>>()?;
+        if params.len() != 4 {
+            return Err(());
+        }
+        let result = MarkovArrivalDescription::Mmpp {
+            generator: vec![vec![-params[2], params[2]], vec![params[3], -params[3]]],
+            rates: vec![params[0], params[1]]
+        };
+        if result.is_valid() { Ok(result) } else { Err(()) }
+    }
+}
@@ -0,0 +1,81 @@
+use rand::Rng;
+use rand::prelude::Distribution;
+use rand_distr::{Exp, Pareto};
+use serde::{Deserialize, Serialize};
+
+/// Superposition of ON/OFF sources with Pareto distributed ON and OFF periods. Every source in ON
+/// period generates Poisson stream of calls. Aggregated stream is long-range dependent with Hurst
+/// parameter H = (3 - α) / 2, where α is the shape of Pareto distribution.
+#[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Debug)]
+pub struct OnOffDescription {
+    /// Number of superposed sources
+    pub sources: usize,
+    /// Hurst parameter in range 0.5..1
+    pub hurst: f64,
+    pub mean_on: f64,
+    pub mean_off: f64
+}
+
+/// State of single ON/OFF source
+#[derive(Clone, Copy, Debug)]
+pub struct OnOffSource {
+    on: bool,
+    /// Time to the end of the current period
+    remaining: f64
+}
+
+impl OnOffDescription {
+    pub fn is_valid(&self) -> bool {
+        self.sources > 0 && self.hurst > 0.5 && self.hurst < 1f64 && self.mean_on > 0f64 && self.mean_off > 0f64
+    }
+
+    /// Shape of Pareto distribution in range 1..2, ON and OFF periods have infinite variance
+    fn shape(&self) -> f64 {
+        3f64 - 2f64 * self.hurst
+    }
+
+    fn period<R: Rng + ?Sized>(&self, rng: &mut R, on: bool) -> f64 {
+        let mean = if on { self.mean_on } else { self.mean_off };
+        let shape = self.shape();
+        Pareto::new(mean * (shape - 1f64) / shape, shape).expect("Wrong ON/OFF period").sample(rng)
+    }
+
+    /// Time to the next arrival of the aggregated stream with mean `intensity`.
+    /// States of sources are kept by the caller between arrivals, they start when `sources` is empty.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, intensity: f64, sources: &mut Vec<OnOffSource>) -> f64 {
+        let p_on = self.mean_on / (self.mean_on + self.mean_off);
+        if sources.is_empty() {
+            for _ in 0..self.sources {
+                let on = rng.gen::<f64>() < p_on;
+                sources.push(OnOffSource { on: on, remaining: self.period(rng, on) });
+            }
+        }
+        // Intensity of single source in ON period
+        let rate = intensity / (self.sources as f64 * p_on);
+
+        let mut time = 0f64;
+        loop {
+            let active = sources.iter().filter(|source| source.on).count();
+            let (switching, end) = sources.iter().enumerate()
+                .map(|(idx, source)| (idx, source.remaining))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            let arrival = if active > 0 {
+                Exp::new(rate * active as f64).expect("Wrong ON/OFF intensity").sample(rng)
+            } else {
+                f64::INFINITY
+            };
+
+            let elapsed = arrival.min(end);
+            time += elapsed;
+            for source in sources.iter_mut() {
+                source.remaining -= elapsed;
+            }
+            if arrival < end {
+                return time;
+            }
+            let on = !sources[switching].on;
+            sources[switching] = OnOffSource { on: on, remaining: self.period(rng, on) };
+        }
+    }
+}
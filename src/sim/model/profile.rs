@@ -0,0 +1,89 @@
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+/// Multiplier of the arrival intensity over simulated time. Inter-arrival times are sampled from the
+/// stationary arrival stream in operational time and mapped to simulated time (time-scaling),
+/// so the type of the stream is kept.
+#[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Debug)]
+pub enum IntensityProfile {
+    /// Multiplier is constant from the start time of the step to the start of the next one.
+    /// The first step starts at time 0. Profile repeats after `period` if given.
+    PiecewiseConstant { steps: Vec<(f64, f64)>, period: Option<f64> },
+    /// Multiplier 1 + amplitude·sin(2π·t/period + phase), amplitude is in range 0..1
+    Sinusoidal { period: f64, amplitude: f64, phase: f64 }
+}
+
+impl IntensityProfile {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            IntensityProfile::PiecewiseConstant { steps, period } => {
+                let ordered = steps.windows(2).all(|pair| pair[0].0 < pair[1].0);
+                let last_start = steps.last().map_or(0f64, |step| step.0);
+                !steps.is_empty() && steps[0].0 == 0f64 && ordered
+                    && steps.iter().all(|step| step.1 >= 0f64)
+                    && period.map_or(true, |period| period > last_start)
+                    // Arrivals can't stop forever
+                    && match period {
+                        Some(_) => steps.iter().any(|step| step.1 > 0f64),
+                        None => steps.last().unwrap().1 > 0f64
+                    }
+            }
+            IntensityProfile::Sinusoidal { period, amplitude, .. } =>
+                *period > 0f64 && (0f64..=1f64).contains(amplitude)
+        }
+    }
+
+    /// Integral of the multiplier from 0 to `time`
+    fn operational_time(&self, time: f64) -> f64 {
+        match self {
+            IntensityProfile::PiecewiseConstant { steps, period } => {
+                let integral = |time: f64| -> f64 {
+                    let mut result = 0f64;
+                    for (idx, step) in steps.iter().enumerate() {
+                        if step.0 >= time {
+                            break;
+                        }
+                        let end = steps.get(idx + 1).map_or(time, |next| next.0.min(time));
+                        result += step.1 * (end - step.0);
+                    }
+                    result
+                };
+                match period {
+                    Some(period) => {
+                        let cycles = (time / period).floor();
+                        let tail = time - cycles * period;
+                        // Last step lasts to the end of the period
+                        cycles * integral(*period) + integral(tail)
+                    }
+                    None => integral(time)
+                }
+            }
+            IntensityProfile::Sinusoidal { period, amplitude, phase } =>
+                time + amplitude * period / (2f64 * PI) * (phase.cos() - (2f64 * PI * time / period + phase).cos())
+        }
+    }
+
+    /// Simulated time that elapses from `time` until `operational_time` of stationary stream passes
+    pub fn scale(&self, time: f64, operational_time: f64) -> f64 {
+        let start = self.operational_time(time);
+        let target = start + operational_time;
+
+        // Operational time is non-decreasing, find the upper bound and bisect
+        let mut low = 0f64;
+        let mut high = operational_time.max(1e-9);
+        while self.operational_time(time + high) < target {
+            low = high;
+            high *= 2f64;
+        }
+        for _ in 0..64 {
+            let mid = 0.5 * (low + high);
+            if self.operational_time(time + mid) < target {
+                low = mid;
+            }
+            else {
+                high = mid;
+            }
+        }
+        high
+    }
+}
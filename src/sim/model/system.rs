@@ -1,11 +1,270 @@
 use core::cmp::Ordering;
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
-use crate::sim::model::class::Class;
+use crate::sim::model::class::{Class, StreamParams};
+
+/// Waiting room in front of the group
+#[derive(PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct QueueDescription {
+    /// Number of waiting places, None for infinite queue
+    pub capacity: Option<usize>,
+    /// Patience of waiting calls, None if calls never abandon the queue
+    pub patience: Option<StreamParams>
+}
+
+impl Eq for QueueDescription {}
+
+/// Retrial orbit for blocked calls
+#[derive(PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RetrialDescription {
+    /// Time between subsequent attempts of blocked call
+    pub delay: StreamParams,
+    /// Probability that blocked call retries instead of leaving the system
+    pub persistence: f64
+}
+
+impl Eq for RetrialDescription {}
+
+/// What happens to the call of lower priority class that was preempted
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum PreemptionPolicy {
+    /// Preempted call waits and resumes its service when capacity is released
+    Resume,
+    /// Preempted call is lost
+    Drop
+}
+
+impl FromStr for PreemptionPolicy {
+    type Err = ();
+    fn from_str(input: &str) -> Result<PreemptionPolicy, Self::Err> {
+        match input {
+            "resume" => Ok(PreemptionPolicy::Resume),
+            "drop"   => Ok(PreemptionPolicy::Drop),
+            _        => Err(()),
+        }
+    }
+}
+
+/// What happens to the call served by the failing server
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum FailurePolicy {
+    /// Call is lost
+    Lost,
+    /// Call is moved to the free server if one exists, otherwise it is lost
+    Move
+}
+
+impl FromStr for FailurePolicy {
+    type Err = ();
+    fn from_str(input: &str) -> Result<FailurePolicy, Self::Err> {
+        match input {
+            "lost" => Ok(FailurePolicy::Lost),
+            "move" => Ok(FailurePolicy::Move),
+            _      => Err(()),
+        }
+    }
+}
+
+/// Failures and repairs of servers (BBUs) of the group
+#[derive(PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct BreakdownDescription {
+    /// Time between repair and the next failure of single server
+    pub failure: StreamParams,
+    /// Repair time of single server
+    pub repair: StreamParams,
+    pub policy: FailurePolicy
+}
+
+impl Eq for BreakdownDescription {}
+
+/// What happens to the call that arrives when only sleeping servers are free
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum SleepPolicy {
+    /// Call wakes up sleeping servers and waits for the end of their setup
+    Wait,
+    /// Call is lost
+    Block
+}
+
+impl FromStr for SleepPolicy {
+    type Err = ();
+    fn from_str(input: &str) -> Result<SleepPolicy, Self::Err> {
+        match input {
+            "wait"  => Ok(SleepPolicy::Wait),
+            "block" => Ok(SleepPolicy::Block),
+            _       => Err(()),
+        }
+    }
+}
+
+/// Idle servers (BBUs) go to sleep, sleeping server needs setup before it serves a call
+#[derive(PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct SleepDescription {
+    /// Setup time of woken up servers
+    pub setup: StreamParams,
+    /// Number of idle servers that stay awake, the other idle servers go to sleep.
+    /// Sleeping servers are woken up in the background to keep this reserve
+    pub awake: usize,
+    pub policy: SleepPolicy
+}
+
+impl Eq for SleepDescription {}
+
+/// How the free server is chosen for the call
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum HuntingPolicy {
+    /// Free server with the smallest index
+    #[default]
+    Sequential,
+    Random,
+    /// Free server with the biggest service rate, the one with the smallest index if there are several
+    FastestFree,
+    /// Server that has been free for the longest time
+    LongestIdle
+}
+
+impl FromStr for HuntingPolicy {
+    type Err = ();
+    fn from_str(input: &str) -> Result<HuntingPolicy, Self::Err> {
+        match input {
+            "sequential"   => Ok(HuntingPolicy::Sequential),
+            "random"       => Ok(HuntingPolicy::Random),
+            "fastest"      => Ok(HuntingPolicy::FastestFree),
+            "longest_idle" => Ok(HuntingPolicy::LongestIdle),
+            _              => Err(()),
+        }
+    }
+}
+
+/// Servers (BBUs) of the group with different service rates
+#[derive(PartialEq, PartialOrd, Clone, Serialize, Deserialize, Debug)]
+pub struct ServersDescription {
+    /// Service rate multiplier of every server, call demanding several servers is served with their mean rate
+    pub rates: Vec<f64>,
+    #[serde(default)]
+    pub hunting: HuntingPolicy
+}
+
+impl Eq for ServersDescription {}
+
+/// Service rate of every call in service depending on the macrostate (number of occupied BBUs)
+/// or on the number of calls in service. Remaining service time of calls is rescaled when the rate changes.
+#[derive(PartialEq, PartialOrd, Clone, Serialize, Deserialize, Debug)]
+pub enum ServiceRateDescription {
+    /// Calls share the total capacity equally, with n calls in service every call is served with rate capacity / n
+    ProcessorSharing { capacity: f64 },
+    /// Rate of every call in macrostates 1, 2, ..., the last rate is used in bigger macrostates
+    StateDependent(Vec<f64>)
+}
+
+impl Eq for ServiceRateDescription {}
+
+impl ServiceRateDescription {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            ServiceRateDescription::ProcessorSharing { capacity } => *capacity > 0f64,
+            ServiceRateDescription::StateDependent(rates) => !rates.is_empty() && rates.iter().all(|rate| *rate > 0f64)
+        }
+    }
+
+    /// Service rate of every call in the macrostate with given number of calls in service
+    pub fn rate(&self, state: usize, calls: usize) -> f64 {
+        match self {
+            ServiceRateDescription::ProcessorSharing { capacity } => capacity / calls.max(1) as f64,
+            ServiceRateDescription::StateDependent(rates) => rates[state.max(1).min(rates.len()) - 1]
+        }
+    }
+}
+
+/// Format: ps:capacity or rates:r1,r2,...
+impl FromStr for ServiceRateDescription {
+    type Err = ();
+    fn from_str(input: &str) -> Result<ServiceRateDescription, Self::Err> {
+        let (kind, params) = input.split_once(':').ok_or(())?;
+        let result = match kind {
+            "ps" => ServiceRateDescription::ProcessorSharing { capacity: f64::from_str(params).map_err(|_| ())? },
+            "rates" => ServiceRateDescription::StateDependent(params.split(',')
+                .map(|rate| f64::from_str(rate.trim()).map_err(|_| ()))
+                .collect::<Result<Vec<f64>, ()>>()?),
+            _ => return Err(())
+        };
+        if result.is_valid() { Ok(result) } else { Err(()) }
+    }
+}
+
+/// Time windows of the statistics, e.g. hours of the day
+#[derive(PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct WindowsDescription {
+    /// Duration of single window
+    pub length: f64,
+    /// Windows repeat after the period (e.g. intensity profile period), they follow one another if not given.
+    /// Without the period neighbouring windows are merged when there are too many of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<f64>
+}
+
+impl Eq for WindowsDescription {}
 
 #[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct ModelDescription {
-    pub class: Class,
-    pub v: usize
+    /// Mix of traffic classes offered to the group
+    pub classes: Vec<Class>,
+    pub v: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueDescription>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrial: Option<RetrialDescription>,
+    /// Preemption between classes of different priority, None if priorities are ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preemption: Option<PreemptionPolicy>,
+    /// Failures and repairs of servers, None if servers never fail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<BreakdownDescription>,
+    /// Statistics in time windows, None if only statistics of the whole simulation are needed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<WindowsDescription>,
+    /// Sleeping servers with setup delays, None if servers are always awake
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleep: Option<SleepDescription>,
+    /// Heterogeneous servers, None if all servers are identical
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servers: Option<ServersDescription>,
+    /// Service rate depending on the macrostate, None if calls are served with unit rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_rate: Option<ServiceRateDescription>
+}
+
+impl ModelDescription {
+    /// Checks if the optional components of the group can be used together and if the infinite queue
+    /// is stable (offered traffic is smaller than the capacity), otherwise describes the problem
+    pub fn check(&self) -> Result<(), String> {
+        let elastic = self.classes.iter().any(|tr_class| tr_class.get_t_min().is_some());
+        if self.classes.iter().any(|tr_class| tr_class.get_batch().is_some() && tr_class.get_sources().is_some()) {
+            return Err("Batch arrivals can't be used with finite number of sources".to_string());
+        }
+        if elastic && (self.queue.is_some() || self.preemption.is_some() || self.breakdown.is_some()) {
+            return Err("Elastic calls can't be used together with the queue, preemption or servers' failures".to_string());
+        }
+        if self.sleep.is_some() && (self.queue.is_some() || self.preemption.is_some() || self.breakdown.is_some() || elastic) {
+            return Err("Sleeping servers can't be used together with the queue, preemption, servers' failures or elastic calls".to_string());
+        }
+        if self.servers.is_some() && (self.queue.is_some() || self.preemption.is_some() || self.breakdown.is_some()
+                                        || elastic || self.sleep.is_some()) {
+            return Err("Heterogeneous servers can't be used together with the queue, preemption, servers' failures, elastic calls or sleeping servers".to_string());
+        }
+        if self.service_rate.is_some() && (self.preemption.is_some() || self.breakdown.is_some() || elastic
+                                             || self.sleep.is_some() || self.servers.is_some()) {
+            return Err("State-dependent service rate can't be used together with preemption, servers' failures, elastic calls, sleeping or heterogeneous servers".to_string());
+        }
+        if let Some(QueueDescription { capacity: None, patience: None }) = self.queue {
+            // Infinite queue of calls that never abandon it grows without bound, unless the offered traffic is limited by sources
+            let traffic: f64 = self.classes.iter().map(|tr_class| tr_class.get_a() * tr_class.get_t() as f64).sum();
+            if self.classes.iter().any(|tr_class| tr_class.get_sources().is_none()) && traffic >= self.v as f64 {
+                return Err(format!("Infinite queue is unstable, offered traffic {} is not smaller than capacity {}", traffic, self.v));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Ord for ModelDescription {
@@ -44,12 +303,42 @@ impl Ord for ModelDescription {
 
 impl PartialOrd for ModelDescription {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-        match self.class.partial_cmp(&other.class) {
-            Some(val) => match val {
-                Ordering::Equal => Some(self.v.cmp(&other.v)),
-                _ => Some(val)
-            }
-            None => None
+        match self.classes.partial_cmp(&other.classes)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.v.cmp(&other.v) {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.queue.partial_cmp(&other.queue)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.retrial.partial_cmp(&other.retrial)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.preemption.partial_cmp(&other.preemption)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.breakdown.partial_cmp(&other.breakdown)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.windows.partial_cmp(&other.windows)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.sleep.partial_cmp(&other.sleep)? {
+            Ordering::Equal => {},
+            val => return Some(val)
+        }
+        match self.servers.partial_cmp(&other.servers)? {
+            Ordering::Equal => {},
+            val => return Some(val)
         }
+        self.service_rate.partial_cmp(&other.service_rate)
     }
 }
\ No newline at end of file
@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use crate::sim::model::class::{Class, StreamParams};
+
+/// Indices of groups occupied by the call at the same time
+pub type Route = Vec<usize>;
+
+/// How the route is chosen from the alternatives of the class
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum AlternativeSelection {
+    /// Alternatives are tried in order, blocked call overflows to the next one
+    #[default]
+    Sequential,
+    /// Random alternative from the ones with enough free BBUs in every group
+    Random,
+    /// Alternative with the biggest number of free BBUs in its most occupied group,
+    /// the first one if there are several
+    LeastLoaded,
+    /// Alternative with the smallest number of free BBUs in its most occupied group (packing),
+    /// the first one if there are several
+    MostLoaded
+}
+
+/// Groups shared by traffic classes. Call of the class is offered to the routes from its
+/// list of alternatives. Call is admitted to the route only if every group of the route has
+/// enough free BBUs. It is lost when no alternative route can admit it.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct TopologyDescription {
+    /// Capacity of every group
+    pub groups: Vec<usize>,
+    pub classes: Vec<Class>,
+    /// Routes available for calls of every class
+    pub alternatives: Vec<Vec<Route>>,
+    #[serde(default)]
+    pub selection: AlternativeSelection,
+    /// Calls move between neighbouring groups (cells), None if calls stay in their route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffDescription>,
+    /// Demand of every class in every group, None if the call demands `t` BBUs of its class in every group of the route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub demands: Option<Vec<Vec<usize>>>
+}
+
+/// Handoff of calls between cells. Every class has the only route with its home cell.
+#[derive(PartialEq, Clone, Serialize, Deserialize, Debug)]
+pub struct HandoffDescription {
+    /// Indices of neighbouring cells of every cell
+    pub neighbours: Vec<Vec<usize>>,
+    /// Number of BBUs of every cell reserved for handoff calls, new call is admitted only if
+    /// more BBUs are free
+    pub guard: Vec<usize>,
+    /// Time that the call spends in the cell before it hands off to the random neighbouring cell
+    pub dwell: StreamParams
+}
+
+impl Eq for HandoffDescription {}
+
+/// Primary group with the traffic classes offered directly to it
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct PrimaryGroupDescription {
+    pub v: usize,
+    pub classes: Vec<Class>
+}
+
+/// Primary groups that overflow to the common secondary group
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct OverflowDescription {
+    pub primary: Vec<PrimaryGroupDescription>,
+    /// Capacity of the secondary group
+    pub secondary: usize
+}
+
+/// Limited-availability group. Call is admitted only if it fits entirely into one subgroup.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct LimitedAvailabilityDescription {
+    /// Capacity of every subgroup
+    pub subgroups: Vec<usize>,
+    pub classes: Vec<Class>,
+    #[serde(default)]
+    pub selection: AlternativeSelection
+}
+
+/// Link of the loss network
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct LinkDescription {
+    pub name: String,
+    pub v: usize
+}
+
+/// Route of the loss network with its own traffic class.
+/// Call occupies `t` BBUs of the class on every link of the route.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct RouteDescription {
+    pub name: String,
+    /// Names of links used by the route
+    pub links: Vec<String>,
+    pub class: Class
+}
+
+/// Loss network, every route uses several links at the same time
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct NetworkDescription {
+    pub links: Vec<LinkDescription>,
+    pub routes: Vec<RouteDescription>
+}
+
+/// Cell with its own traffic classes
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct CellDescription {
+    pub v: usize,
+    /// Number of BBUs reserved for handoff calls
+    #[serde(default)]
+    pub guard: usize,
+    /// Indices of neighbouring cells
+    pub neighbours: Vec<usize>,
+    pub classes: Vec<Class>
+}
+
+/// Cellular network, calls hand off to neighbouring cells after the dwell time
+#[derive(PartialEq, Clone, Serialize, Deserialize, Debug)]
+pub struct CellularDescription {
+    pub cells: Vec<CellDescription>,
+    pub dwell: StreamParams
+}
+
+impl Eq for CellularDescription {}
+
+/// Connection between the input and output switch of the switching network
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct ConnectionDescription {
+    pub input: usize,
+    pub output: usize,
+    pub class: Class
+}
+
+/// Three-stage Clos switching network. Every input switch is connected with every middle switch,
+/// and every middle switch with every output switch. Connection occupies `t` BBUs of the inlet of
+/// its input switch, of the outlet of its output switch and of both links through the middle switch.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct ClosDescription {
+    /// Number of input switches, it is also the number of output switches
+    pub switches: usize,
+    /// Capacity of inlets of every input switch and of outlets of every output switch
+    pub inlets: usize,
+    /// Number of middle switches
+    pub middle: usize,
+    /// Capacity of every link between stages
+    pub link: usize,
+    pub connections: Vec<ConnectionDescription>,
+    /// Path hunting through the middle switches
+    #[serde(default)]
+    pub selection: AlternativeSelection
+}
+
+/// Resource of the pool, e.g. CPU, RAM or bandwidth
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct ResourceDescription {
+    pub name: String,
+    pub v: usize
+}
+
+/// Traffic class of the resource pool with the demand of every resource
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct ResourceClassDescription {
+    /// Demand of every resource in the order of the pool description
+    pub demands: Vec<usize>,
+    pub class: Class
+}
+
+/// Pool of several resources. Call is admitted only if its demand of every resource fits.
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub struct ResourcePoolDescription {
+    pub resources: Vec<ResourceDescription>,
+    pub classes: Vec<ResourceClassDescription>
+}
+
+/// Configuration file of the multi-group simulation
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize, Debug)]
+pub enum TopologyConfig {
+    Overflow(OverflowDescription),
+    LimitedAvailability(LimitedAvailabilityDescription),
+    Network(NetworkDescription),
+    Cellular(CellularDescription),
+    Clos(ClosDescription),
+    ResourcePool(ResourcePoolDescription)
+}
+
+impl OverflowDescription {
+    /// Secondary group is the last one, after all primary groups
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        let secondary_idx = self.primary.len();
+        let mut result = TopologyDescription {
+            groups: self.primary.iter().map(|group| group.v).collect(),
+            classes: Vec::new(),
+            alternatives: Vec::new(),
+            selection: AlternativeSelection::Sequential,
+            handoff: None,
+            demands: None
+        };
+        result.groups.push(self.secondary);
+
+        for (group_idx, group) in self.primary.iter().enumerate() {
+            for tr_class in &group.classes {
+                result.classes.push(tr_class.clone());
+                result.alternatives.push(vec![vec![group_idx], vec![secondary_idx]]);
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl LimitedAvailabilityDescription {
+    /// Every subgroup is the alternative for every class
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        Ok(TopologyDescription {
+            groups: self.subgroups.clone(),
+            classes: self.classes.clone(),
+            alternatives: vec![(0..self.subgroups.len()).map(|idx| vec![idx]).collect(); self.classes.len()],
+            selection: self.selection,
+            handoff: None,
+            demands: None
+        })
+    }
+}
+
+impl NetworkDescription {
+    /// Links are groups and every route has its own class. Fails if the route uses unknown link.
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        let mut result = TopologyDescription {
+            groups: self.links.iter().map(|link| link.v).collect(),
+            classes: Vec::new(),
+            alternatives: Vec::new(),
+            selection: AlternativeSelection::Sequential,
+            handoff: None,
+            demands: None
+        };
+
+        for route in &self.routes {
+            let mut links = Vec::new();
+            for name in &route.links {
+                match self.links.iter().position(|link| link.name == *name) {
+                    Some(idx) => links.push(idx),
+                    None => return Err(format!("Route {} uses unknown link {}", route.name, name))
+                }
+            }
+            result.classes.push(route.class.clone());
+            result.alternatives.push(vec![links]);
+        }
+        Ok(result)
+    }
+}
+
+impl CellularDescription {
+    /// Cells are groups and every class is offered only to its home cell
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        let mut result = TopologyDescription {
+            groups: self.cells.iter().map(|cell| cell.v).collect(),
+            classes: Vec::new(),
+            alternatives: Vec::new(),
+            selection: AlternativeSelection::Sequential,
+            handoff: Some(HandoffDescription {
+                neighbours: self.cells.iter().map(|cell| cell.neighbours.clone()).collect(),
+                guard: self.cells.iter().map(|cell| cell.guard).collect(),
+                dwell: self.dwell
+            }),
+            demands: None
+        };
+
+        for (cell_idx, cell) in self.cells.iter().enumerate() {
+            for tr_class in &cell.classes {
+                result.classes.push(tr_class.clone());
+                result.alternatives.push(vec![vec![cell_idx]]);
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl ClosDescription {
+    /// Groups are inlets of input switches, outlets of output switches, links from input to middle
+    /// switches and links from middle to output switches. Every middle switch gives the alternative
+    /// route of the connection. Fails if the connection uses unknown switch.
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        let (r, m) = (self.switches, self.middle);
+        let first_stage = |input: usize, middle: usize| 2 * r + input * m + middle;
+        let second_stage = |middle: usize, output: usize| 2 * r + r * m + middle * r + output;
+
+        let mut result = TopologyDescription {
+            groups: vec![self.inlets; 2 * r],
+            classes: Vec::new(),
+            alternatives: Vec::new(),
+            selection: self.selection,
+            handoff: None,
+            demands: None
+        };
+        result.groups.extend(vec![self.link; 2 * r * m]);
+
+        for connection in &self.connections {
+            if connection.input >= r || connection.output >= r {
+                return Err(format!("Connection {} -> {} uses unknown switch, number of switches is {}", connection.input, connection.output, r));
+            }
+            result.classes.push(connection.class.clone());
+            result.alternatives.push((0..m)
+                .map(|middle| vec![connection.input, r + connection.output,
+                                   first_stage(connection.input, middle), second_stage(middle, connection.output)])
+                .collect());
+        }
+        Ok(result)
+    }
+}
+
+impl ResourcePoolDescription {
+    /// Resources are groups and every class has the only route with resources it demands.
+    /// Fails if the class doesn't give demand of every resource or demands nothing.
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        let mut result = TopologyDescription {
+            groups: self.resources.iter().map(|resource| resource.v).collect(),
+            classes: Vec::new(),
+            alternatives: Vec::new(),
+            selection: AlternativeSelection::Sequential,
+            handoff: None,
+            demands: None
+        };
+
+        let mut demands = Vec::new();
+        for resource_class in &self.classes {
+            if resource_class.demands.len() != self.resources.len() || resource_class.demands.iter().all(|demand| *demand == 0) {
+                return Err(format!("Wrong class demands {:?}, number of resources is {}", resource_class.demands, self.resources.len()));
+            }
+            result.classes.push(resource_class.class.clone());
+            result.alternatives.push(vec![(0..self.resources.len()).filter(|idx| resource_class.demands[*idx] > 0).collect()]);
+            demands.push(resource_class.demands.clone());
+        }
+        result.demands = Some(demands);
+        Ok(result)
+    }
+}
+
+impl TopologyConfig {
+    pub fn topology(&self) -> Result<TopologyDescription, String> {
+        match self {
+            TopologyConfig::Overflow(overflow) => overflow.topology(),
+            TopologyConfig::LimitedAvailability(group) => group.topology(),
+            TopologyConfig::Network(network) => network.topology(),
+            TopologyConfig::Cellular(cellular) => cellular.topology(),
+            TopologyConfig::Clos(clos) => clos.topology(),
+            TopologyConfig::ResourcePool(pool) => pool.topology()
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How the times are drawn from the trace
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum TraceMode {
+    /// Samples follow each other like in the trace, correlation between them is kept
+    #[default]
+    Replay,
+    /// Samples are drawn independently with replacement
+    Bootstrap
+}
+
+/// Recorded inter-arrival or holding times. Samples are read from the file, one value per line or
+/// a column of CSV file. The trace is identified by the hash of the file content, not by its path,
+/// so classes with the same trace are equal wherever the file is.
+/// Trace given only by its path (e.g. in the configuration file) is read, when it is deserialized.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(try_from = "StoredTrace")]
+pub struct TraceDescription {
+    pub path: String,
+    /// Column of CSV file, None if the file has one value per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(default)]
+    pub mode: TraceMode,
+    /// FNV-1a hash of the file content
+    pub hash: String,
+    pub no_of_samples: usize,
+    /// Empirical mean of samples
+    pub mean: f64,
+    /// Empirical E²/D² of samples
+    pub e2d2: f64
+}
+
+/// Trace as it is written, hash, number of samples and moments are missing if the trace wasn't read yet
+#[derive(Deserialize)]
+struct StoredTrace {
+    path: String,
+    #[serde(default)]
+    column: Option<usize>,
+    #[serde(default)]
+    mode: TraceMode,
+    hash: Option<String>,
+    no_of_samples: Option<usize>,
+    mean: Option<f64>,
+    e2d2: Option<f64>
+}
+
+impl TryFrom<StoredTrace> for TraceDescription {
+    type Error = String;
+    fn try_from(stored: StoredTrace) -> Result<Self, Self::Error> {
+        match stored {
+            StoredTrace { path, column, mode, hash: Some(hash), no_of_samples: Some(no_of_samples), mean: Some(mean), e2d2: Some(e2d2) } =>
+                Ok(TraceDescription { path, column, mode, hash, no_of_samples, mean, e2d2 }),
+            StoredTrace { path, column, mode, .. } => TraceDescription::load(&path, column, mode)
+        }
+    }
+}
+
+/// Samples of the trace scaled to the mean of the stream
+#[derive(Clone, Debug)]
+pub struct TraceStream {
+    samples: Arc<[f64]>,
+    mode: TraceMode
+}
+
+/// 64-bit FNV-1a hash, it doesn't change between builds
+fn content_hash(content: &[u8]) -> String {
+    let hash = content.iter().fold(0xcbf29ce484222325u64, |hash, byte|
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+/// Parses samples from the file content. The first line is skipped if it is not a number (CSV header).
+fn parse_samples(content: &str, column: Option<usize>) -> Result<Vec<f64>, String> {
+    let mut samples = Vec::new();
+    for (line_no, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let field = match column {
+            Some(column) => line.split(',').nth(column)
+                .ok_or(format!("line {}: there is no column {}", line_no + 1, column))?,
+            None => line
+        };
+        match f64::from_str(field.trim()) {
+            Ok(sample) if sample >= 0f64 && sample.is_finite() => samples.push(sample),
+            Ok(sample) => return Err(format!("line {}: wrong time {}", line_no + 1, sample)),
+            Err(_) if line_no == 0 => {},
+            Err(_) => return Err(format!("line {}: \"{}\" is not a number", line_no + 1, field.trim()))
+        }
+    }
+    Ok(samples)
+}
+
+impl TraceDescription {
+    /// Reads the trace and computes its hash and moments
+    pub fn load(path: &str, column: Option<usize>, mode: TraceMode) -> Result<Self, String> {
+        let content = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        let samples = parse_samples(&String::from_utf8_lossy(&content), column)?;
+        if samples.len() < 2 {
+            return Err(format!("{}: at least 2 samples are required", path));
+        }
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (samples.len() - 1) as f64;
+        if mean <= 0f64 || variance <= 0f64 {
+            return Err(format!("{}: samples have mean {} and variance {}, use deterministic stream for constant times", path, mean, variance));
+        }
+        Ok(TraceDescription {
+            path: path.to_string(),
+            column: column,
+            mode: mode,
+            hash: content_hash(&content),
+            no_of_samples: samples.len(),
+            mean: mean,
+            e2d2: mean * mean / variance
+        })
+    }
+
+    /// Reads samples again and scales them to `mean`. Fails if the file content has changed.
+    pub fn stream(&self, mean: f64) -> Result<TraceStream, String> {
+        let content = fs::read(&self.path).map_err(|e| format!("{}: {}", self.path, e))?;
+        if content_hash(&content) != self.hash {
+            return Err(format!("{}: content doesn't match the trace hash {}", self.path, self.hash));
+        }
+        let factor = mean / self.mean;
+        let samples: Vec<f64> = parse_samples(&String::from_utf8_lossy(&content), self.column)?
+            .into_iter()
+            .map(|sample| sample * factor)
+            .collect();
+        Ok(TraceStream { samples: samples.into(), mode: self.mode })
+    }
+
+    fn key(&self) -> (&String, Option<usize>, TraceMode) {
+        (&self.hash, self.column, self.mode)
+    }
+}
+
+impl PartialEq for TraceDescription {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl PartialOrd for TraceDescription {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key().partial_cmp(&other.key())
+    }
+}
+
+impl TraceStream {
+    /// Next time of the trace. `position` of the replayed trace is kept by the caller,
+    /// replay starts at random sample if it is None, so subsequent simulations don't repeat the same times.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, position: &mut Option<usize>) -> f64 {
+        match self.mode {
+            TraceMode::Replay => {
+                let idx = position.unwrap_or_else(|| rng.gen_range(0..self.samples.len()));
+                *position = Some((idx + 1) % self.samples.len());
+                self.samples[idx]
+            }
+            TraceMode::Bootstrap => self.samples[rng.gen_range(0..self.samples.len())]
+        }
+    }
+}
+
+impl FromStr for TraceMode {
+    type Err = ();
+    fn from_str(input: &str) -> Result<TraceMode, Self::Err> {
+        match input {
+            "replay"    => Ok(TraceMode::Replay),
+            "bootstrap" => Ok(TraceMode::Bootstrap),
+            _           => Err(()),
+        }
+    }
+}
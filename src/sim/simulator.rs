@@ -1,22 +1,57 @@
 pub mod scheduler;
 pub mod process;
 pub mod system;
+pub mod queue;
+pub mod orbit;
+pub mod breakdown;
+pub mod compression;
+pub mod windows;
+pub mod sleep;
+pub mod servers;
+pub mod sharing;
+pub mod topology;
 pub mod single_statistics;
 pub mod simulations_statistics;
 
 use system::Group;
+use queue::Queue;
+use orbit::Orbit;
+use breakdown::Breakdown;
+use compression::Compression;
+use windows::Windows;
+use sleep::Sleep;
+use servers::Servers;
+use sharing::Sharing;
+use topology::Topology;
 use rand::rngs::ThreadRng;
 use scheduler::Scheduler;
-use crate::sim::model::class::sim_class::SimClass;
-use crate::sim::simulator::single_statistics::StatisticsFinalized;
+use crate::sim::model::class::sim_class::{ArrivalState, SimClass};
+use crate::sim::model::system::{ModelDescription, PreemptionPolicy};
+use crate::sim::simulator::process::CallInService;
+use crate::sim::model::topology::TopologyDescription;
+use crate::sim::simulator::single_statistics::{StatisticsFinalized, TopologyStatistics};
 
 #[derive(Clone)]
 pub struct Simulator<'a> {
     pub group: Group,
+    /// Several groups shared by classes, replaces the single group if present
+    pub topology: Option<Topology>,
+    /// Optional retrial orbit for blocked calls
+    pub orbit: Option<Orbit>,
+    /// Preemption between classes of different priority
+    pub preemption: Option<PreemptionPolicy>,
+    /// Calls in service, tracked only if preemption, servers' failures or elastic calls are enabled
+    pub in_service: Vec<CallInService>,
     pub scheduler: Scheduler<'a>,
+    /// Simulation time since the start, it drives intensity profiles of arrival streams
+    pub time: f64,
+    /// State of non-renewal arrival process of every class
+    pub arrival_states: Vec<ArrivalState>,
+    /// Position in the replayed service trace of every class
+    pub service_traces: Vec<Option<usize>>,
     pub rng: ThreadRng,
     pub no_of_lost_calls: u32,
-    pub tr_class: &'a SimClass,
+    pub tr_classes: &'a [SimClass],
     pub total_lost: u64,
     pub total_serv: u64,
 
@@ -27,26 +62,93 @@ pub struct Simulator<'a> {
 
 impl <'a>Simulator<'a>
 {
-    pub fn new(tr_class:&'a SimClass, v:usize) -> Simulator<'a> {
-        Simulator {
-            group: Group::new(v),
+    /// Returns the reason if components of the model can't be used together or their parameters are wrong
+    pub fn new(tr_classes:&'a [SimClass], model: &ModelDescription) -> Result<Simulator<'a>, String> {
+        model.check()?;
+        let mut group = Group::new(model.v);
+        group.queue = model.queue.as_ref().map(|queue|
+            Queue::new(queue).ok_or("Wrong patience stream parameters")).transpose()?;
+        group.breakdown = model.breakdown.as_ref().map(|breakdown|
+            Breakdown::new(breakdown, model.v).ok_or("Wrong failure or repair stream parameters")).transpose()?;
+        group.windows = model.windows.as_ref().map(Windows::new).transpose()?;
+        if model.classes.iter().any(|tr_class| tr_class.get_t_min().is_some()) {
+            group.compression = Some(Compression::new(model.v, &model.classes)?);
+        }
+        group.sleep = model.sleep.as_ref().map(|sleep| Sleep::new(sleep, model.v)).transpose()?;
+        group.servers = model.servers.as_ref().map(|servers| Servers::new(servers, model.v)).transpose()?;
+        group.sharing = model.service_rate.as_ref().map(Sharing::new).transpose()?;
+
+        Ok(Simulator {
+            group: group,
+            topology: None,
+            orbit: model.retrial.as_ref().map(|retrial|
+                Orbit::new(retrial).ok_or("Wrong retrial stream parameters")).transpose()?,
+            preemption: model.preemption,
+            in_service: Vec::new(),
             scheduler: Scheduler::new(),
+            time: 0f64,
+            arrival_states: vec![ArrivalState::default(); tr_classes.len()],
+            service_traces: vec![None; tr_classes.len()],
             rng: ThreadRng::default(),
             no_of_lost_calls: 0,
-            tr_class: tr_class,
+            tr_classes: tr_classes,
             total_lost: 0,
             total_serv: 0,
             min_occurrance: 0,
             analyze_states: false,
             check_cntr: 100
+        })
+    }
+
+    /// Simulator of several groups. Waiting room, retrial orbit and preemption are not used.
+    /// Returns the reason if the topology is wrong.
+    pub fn with_topology(tr_classes:&'a [SimClass], topology: &TopologyDescription) -> Result<Simulator<'a>, String> {
+        if topology.classes.iter().any(|tr_class| tr_class.get_batch().is_some()) {
+            return Err("Batch arrivals can't be used with topology of several groups".to_string());
         }
+        Ok(Simulator {
+            group: Group::new(0),
+            topology: Some(Topology::new(topology)?),
+            orbit: None,
+            preemption: None,
+            in_service: Vec::new(),
+            scheduler: Scheduler::new(),
+            time: 0f64,
+            arrival_states: vec![ArrivalState::default(); tr_classes.len()],
+            service_traces: vec![None; tr_classes.len()],
+            rng: ThreadRng::default(),
+            no_of_lost_calls: 0,
+            tr_classes: tr_classes,
+            total_lost: 0,
+            total_serv: 0,
+            min_occurrance: 0,
+            analyze_states: false,
+            check_cntr: 100
+        })
     }
 
     pub fn prepare_simulation(&mut self) {
-        let first_process = process::SimProcess::new(& self.tr_class);
-        self.scheduler.add_process(first_process);
+        for (class_idx, tr_class) in self.tr_classes.iter().enumerate() {
+            // Every finite source has its own arrival process
+            for _ in 0..tr_class.get_sources().unwrap_or(1) {
+                let first_process = process::SimProcess::new(tr_class, class_idx);
+                self.scheduler.add_process(first_process);
+            }
+        }
+        if let Some(breakdown) = &self.group.breakdown {
+            // Every server has its own failure process
+            for _ in 0..self.group.v {
+                let time = breakdown.get_failure_time(&mut self.rng);
+                let process = process::SimProcess::with_state(process::State::WaitForFailure, time, &self.tr_classes[0], 0);
+                self.scheduler.add_process(process);
+            }
+        }
 
-        let mut no_of_proc_call =10_000 * self.group.v;
+        let capacity = match &self.topology {
+            Some(topology) => topology.capacity(),
+            None => self.group.v
+        };
+        let mut no_of_proc_call =10_000 * capacity;
 
         while no_of_proc_call > 0 {
             let evnt = self.scheduler.get_process();
@@ -56,7 +158,16 @@ impl <'a>Simulator<'a>
     }
 
     pub fn simulate_with_statistics(&mut self, min_state_cntr: u32) {
-        self.group.statistics_init();
+        let demands: Vec<(usize, Option<usize>)> = self.tr_classes.iter()
+            .map(|tr_class| (tr_class.get_t(), tr_class.get_sources()))
+            .collect();
+        match &mut self.topology {
+            Some(topology) => topology.statistics_init(&demands),
+            None => self.group.statistics_init(&demands)
+        }
+        if let Some(orbit) = &mut self.orbit {
+            orbit.statistics_init();
+        }
 
         self.min_occurrance = min_state_cntr;
         self.analyze_states = true;
@@ -74,7 +185,28 @@ impl <'a>Simulator<'a>
         }
     }
     pub fn prepare_statistics(&self) -> StatisticsFinalized {
-        self.group.statistics_preview(self.total_lost + self.total_serv, self.group.min_state_occurance() as u32)
+        let mut result = self.group.statistics_preview(self.total_lost + self.total_serv, self.group.min_state_occurance() as u32);
+        result.orbit = self.orbit.as_ref().and_then(|orbit| orbit.statistics_preview());
+        result
+    }
+
+    /// Calls in service are tracked when they can be preempted, interrupted, compressed or their service rate changes
+    pub fn tracks_calls_in_service(&self) -> bool {
+        self.preemption.is_some() || self.group.breakdown.is_some() || self.group.compression.is_some() || self.group.sharing.is_some()
+    }
+
+    pub fn prepare_topology_statistics(&mut self) -> TopologyStatistics {
+        let no_of_events = self.total_lost + self.total_serv;
+        let topology = self.topology.as_mut().expect("Simulator without topology");
+        let min_state_occurance = topology.min_state_occurance() as u32;
+        topology.statistics_preview(no_of_events, min_state_occurance)
+    }
+
+    fn min_state_occurance(&self) -> usize {
+        match &self.topology {
+            Some(topology) => topology.min_state_occurance(),
+            None => self.group.min_state_occurance()
+        }
     }
 
     pub fn end_simulation(&mut self) -> bool {
@@ -86,7 +218,7 @@ impl <'a>Simulator<'a>
             {
                 let min_ocur;
                 self.check_cntr = 100;
-                min_ocur = self.group.min_state_occurance() as u32;
+                min_ocur = self.min_state_occurance() as u32;
                 result = min_ocur >= self.min_occurrance;
             }
         }
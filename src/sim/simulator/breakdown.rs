@@ -0,0 +1,142 @@
+use rand::Rng;
+
+use crate::sim::model::class::StreamOfEvents;
+use crate::sim::model::system::{BreakdownDescription, FailurePolicy};
+use crate::sim::simulator::single_statistics::{AvailabilityStatisticsRaw, AvailabilityStatistics};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ServerState {
+    Idle,
+    /// Server serves the call with given identifier
+    Busy(u64),
+    Failed
+}
+
+/// Failures and repairs of servers (BBUs) of the group. Every server fails independently,
+/// no matter whether it is busy or idle.
+#[derive(Clone)]
+pub struct Breakdown
+{
+    failure: StreamOfEvents,
+    repair: StreamOfEvents,
+    pub policy: FailurePolicy,
+    /// Number of failed servers
+    failed: usize,
+    servers: Vec<ServerState>,
+    statistics: Option<AvailabilityStatisticsRaw>
+}
+
+impl Breakdown {
+    /// Returns None if failure or repair stream parameters are not valid
+    pub fn new(description: &BreakdownDescription, v: usize) -> Option<Self> {
+        Some(Breakdown {
+            failure: description.failure.get_stream()?,
+            repair: description.repair.get_stream()?,
+            policy: description.policy,
+            failed: 0,
+            servers: vec![ServerState::Idle; v],
+            statistics: None
+        })
+    }
+
+    pub fn get_failed(&self) -> usize {
+        self.failed
+    }
+
+    pub fn get_failure_time<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.failure.sample(rng)
+    }
+
+    pub fn get_repair_time<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.repair.sample(rng)
+    }
+
+    pub fn time_update(&mut self, busy: usize, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.update_time(self.failed, busy, time_delta);
+        }
+    }
+
+    /// Call with given identifier takes `t` idle servers when its service starts
+    pub fn bind(&mut self, call_id: u64, t: usize) {
+        for server in self.servers.iter_mut().filter(|server| **server == ServerState::Idle).take(t) {
+            *server = ServerState::Busy(call_id);
+        }
+    }
+
+    /// Call with given identifier left the servers, e.g. it ended its service or it was preempted
+    pub fn call_end(&mut self, call_id: u64) {
+        for server in self.servers.iter_mut().filter(|server| **server == ServerState::Busy(call_id)) {
+            *server = ServerState::Idle;
+        }
+    }
+
+    /// Server chosen uniformly among working servers fails. Its call is moved to the idle server if policy allows.
+    /// Returns identifier of the call that has to be interrupted, the other servers of the call are idle then.
+    /// Counters of failed servers are updated by `server_failed`.
+    pub fn server_fail<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<u64> {
+        let working: Vec<usize> = (0..self.servers.len()).filter(|idx| self.servers[*idx] != ServerState::Failed).collect();
+        let server = working[rng.gen_range(0..working.len())];
+        let call_id = match self.servers[server] {
+            ServerState::Busy(call_id) => call_id,
+            _ => {
+                self.servers[server] = ServerState::Failed;
+                return None;
+            }
+        };
+        self.servers[server] = ServerState::Failed;
+        let idle = self.servers.iter().position(|server| *server == ServerState::Idle);
+        match idle {
+            Some(idle) if self.policy == FailurePolicy::Move => {
+                self.servers[idle] = ServerState::Busy(call_id);
+                None
+            }
+            _ => {
+                self.call_end(call_id);
+                Some(call_id)
+            }
+        }
+    }
+
+    pub fn server_failed(&mut self) {
+        self.failed += 1;
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_failures += 1;
+        }
+    }
+
+    pub fn server_repaired(&mut self) {
+        self.failed -= 1;
+        if let Some(server) = self.servers.iter_mut().find(|server| **server == ServerState::Failed) {
+            *server = ServerState::Idle;
+        }
+    }
+
+    /// Accounts new call. Blocked call is blocked due to reduced capacity if it would be
+    /// admitted when all servers worked, otherwise due to congestion.
+    pub fn call_offered(&mut self, blocked: bool, fits_without_failures: bool) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_calls += 1;
+            if blocked && fits_without_failures {
+                stat.no_of_blocked_capacity += 1;
+            }
+            else if blocked {
+                stat.no_of_blocked_congestion += 1;
+            }
+        }
+    }
+
+    pub fn call_interrupted(&mut self) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_interrupted_calls += 1;
+        }
+    }
+
+    pub fn statistics_init(&mut self, v: usize) {
+        self.statistics = Some(AvailabilityStatisticsRaw::new(v));
+    }
+
+    pub fn statistics_preview(&self) -> Option<AvailabilityStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_availability_statistics())
+    }
+}
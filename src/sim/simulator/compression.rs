@@ -0,0 +1,162 @@
+use crate::sim::model::class::Class;
+use crate::sim::simulator::system::Group;
+use crate::sim::simulator::single_statistics::{CompressionStatisticsRaw, CompressionStatistics};
+
+/// Elastic calls in the group. Macrostate is the nominal number of BBUs demanded by calls in service,
+/// it can exceed the group capacity. All elastic calls are compressed with the same ratio
+/// min(1, (v - fixed) / elastic), where `fixed` and `elastic` are nominal numbers of BBUs demanded by
+/// calls that don't compress and by elastic calls. New call is admitted if the ratio doesn't drop
+/// below the biggest minimum ratio t_min / t of elastic calls in service.
+#[derive(Clone)]
+pub struct Compression
+{
+    v: usize,
+    /// Minimum to nominal demand ratio of every class, None for classes that don't compress
+    min_ratio: Vec<Option<f64>>,
+    /// Number of calls of every class in service
+    calls: Vec<usize>,
+    fixed: usize,
+    elastic: usize,
+    /// Compression ratio and number of carried BBUs since the last statistics update
+    ratio: f64,
+    carried: f64,
+    statistics: Option<CompressionStatisticsRaw>
+}
+
+impl Compression {
+    /// Returns the reason if minimum demand of elastic class is not in range 1..t
+    pub fn new(v: usize, classes: &[Class]) -> Result<Self, String> {
+        let mut min_ratio = Vec::new();
+        for tr_class in classes {
+            match tr_class.get_t_min() {
+                Some(t_min) if t_min == 0 || t_min > tr_class.get_t() => {
+                    return Err(format!("Minimum demand {} of elastic class is not in range 1..{}", t_min, tr_class.get_t()));
+                }
+                Some(t_min) => min_ratio.push(Some(t_min as f64 / tr_class.get_t() as f64)),
+                None => min_ratio.push(None)
+            }
+        }
+
+        Ok(Compression {
+            v: v,
+            calls: vec![0; min_ratio.len()],
+            min_ratio: min_ratio,
+            fixed: 0,
+            elastic: 0,
+            ratio: 1f64,
+            carried: 0f64,
+            statistics: None
+        })
+    }
+
+    /// The biggest nominal number of BBUs demanded by calls in service
+    pub fn max_occupancy(&self) -> usize {
+        let min_ratio = self.min_ratio.iter().flatten().cloned().fold(1f64, f64::min);
+        ((self.v as f64 / min_ratio + 1e-9).floor() as usize).max(self.v)
+    }
+
+    /// Nominal number of BBUs demanded by calls in service
+    pub fn occupancy(&self) -> usize {
+        self.fixed + self.elastic
+    }
+
+    /// Number of BBUs that are not allocated to any call
+    pub fn get_v_free(&self) -> usize {
+        self.v - self.fixed - self.elastic.min(self.v - self.fixed)
+    }
+
+    /// Ratio of allocated to nominal bandwidth of elastic calls
+    pub fn get_ratio(&self) -> f64 {
+        if self.elastic == 0 {
+            return 1f64;
+        }
+        ((self.v - self.fixed) as f64 / self.elastic as f64).min(1f64)
+    }
+
+    pub fn is_elastic(&self, class_idx: usize) -> bool {
+        self.min_ratio[class_idx].is_some()
+    }
+
+    pub fn can_admit(&self, class_idx: usize, t: usize) -> bool {
+        let (fixed, elastic) = match self.min_ratio[class_idx] {
+            Some(_) => (self.fixed, self.elastic + t),
+            None => (self.fixed + t, self.elastic)
+        };
+        if fixed > self.v {
+            return false;
+        }
+        let max_ratio = self.min_ratio.iter().zip(self.calls.iter()).enumerate()
+            .filter(|(idx, (_, calls))| **calls > 0 || *idx == class_idx)
+            .filter_map(|(_, (ratio, _))| *ratio)
+            .fold(0f64, f64::max);
+        fixed as f64 + max_ratio * elastic as f64 <= self.v as f64 + 1e-9
+    }
+
+    pub fn call_add(&mut self, class_idx: usize, t: usize) {
+        self.calls[class_idx] += 1;
+        match self.min_ratio[class_idx] {
+            Some(_) => self.elastic += t,
+            None => self.fixed += t
+        }
+    }
+
+    pub fn call_end(&mut self, class_idx: usize, t: usize) {
+        self.calls[class_idx] -= 1;
+        match self.min_ratio[class_idx] {
+            Some(_) => self.elastic -= t,
+            None => self.fixed -= t
+        }
+    }
+
+    /// Accounts time spent in the macrostate with compression ratio from the previous update
+    pub fn time_update(&mut self, state: usize, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.update_time(state, self.ratio, self.carried, time_delta);
+        }
+        self.ratio = self.get_ratio();
+        self.carried = self.fixed as f64 + self.ratio * self.elastic as f64;
+    }
+
+    /// Marks macrostates that can be reached by offered classes.
+    /// Every class is described by its demand `t` and optional limit of calls (finite sources).
+    pub fn reachable_states(&self, demands: &[(usize, Option<usize>)]) -> Vec<bool> {
+        let max_occupancy = self.max_occupancy();
+        let fixed_demands: Vec<(usize, Option<usize>)> = demands.iter().zip(self.min_ratio.iter())
+            .map(|((t, max_calls), ratio)| if ratio.is_none() { (*t, *max_calls) } else { (*t, Some(0)) })
+            .collect();
+        let fixed_reachable = Group::reachable_states(self.v, &fixed_demands);
+
+        let mut result = vec![false; max_occupancy + 1];
+        for (fixed, _) in fixed_reachable.iter().enumerate().filter(|(_, reachable)| **reachable) {
+            result[fixed] = true;
+        }
+
+        // Elastic calls of the class with the biggest minimum ratio limit the macrostate
+        for max_ratio in self.min_ratio.iter().flatten() {
+            let elastic_demands: Vec<(usize, Option<usize>)> = demands.iter().zip(self.min_ratio.iter())
+                .map(|((t, max_calls), ratio)| match ratio {
+                    Some(ratio) if ratio <= max_ratio => (*t, *max_calls),
+                    _ => (*t, Some(0))
+                })
+                .collect();
+            let elastic_reachable = Group::reachable_states(max_occupancy, &elastic_demands);
+
+            for (fixed, _) in fixed_reachable.iter().enumerate().filter(|(_, reachable)| **reachable) {
+                for (elastic, _) in elastic_reachable.iter().enumerate().filter(|(_, reachable)| **reachable) {
+                    if fixed as f64 + max_ratio * elastic as f64 <= self.v as f64 + 1e-9 {
+                        result[fixed + elastic] = true;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn statistics_init(&mut self) {
+        self.statistics = Some(CompressionStatisticsRaw::new(self.max_occupancy() + 1));
+    }
+
+    pub fn statistics_preview(&self) -> Option<CompressionStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_compression_statistics())
+    }
+}
@@ -0,0 +1,97 @@
+use rand::Rng;
+
+use crate::sim::model::class::StreamOfEvents;
+use crate::sim::model::system::RetrialDescription;
+use crate::sim::simulator::single_statistics::{OrbitStatisticsRaw, OrbitStatistics};
+
+/// Retrial orbit. Blocked calls stay in the orbit and repeat the attempt after random delay.
+#[derive(Clone)]
+pub struct Orbit
+{
+    delay: StreamOfEvents,
+    /// Probability that blocked call retries instead of leaving the system
+    persistence: f64,
+    /// Number of calls in the orbit
+    size: usize,
+    statistics: Option<OrbitStatisticsRaw>
+}
+
+impl Orbit {
+    /// Returns None if retrial delay stream parameters are not valid
+    pub fn new(description: &RetrialDescription) -> Option<Self> {
+        Some(Orbit {
+            delay: description.delay.get_stream()?,
+            persistence: description.persistence,
+            size: 0,
+            statistics: None
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn time_update(&mut self, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.update_time(self.size, time_delta);
+        }
+    }
+
+    /// Accounts first attempt of new call
+    pub fn primary_call(&mut self, admitted: bool) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_primary_calls += 1;
+            if admitted {
+                stat.no_of_successful_calls += 1;
+            }
+            else {
+                stat.no_of_primary_calls_blocked += 1;
+            }
+        }
+    }
+
+    /// Accounts repeated attempt of the call from the orbit
+    pub fn repeated_call(&mut self, admitted: bool) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_repeated_calls += 1;
+            if admitted {
+                stat.no_of_successful_calls += 1;
+            }
+            else {
+                stat.no_of_repeated_calls_blocked += 1;
+            }
+        }
+        if admitted {
+            self.size -= 1;
+        }
+    }
+
+    /// Call was blocked. Returns time to the next attempt, or None if the call gives up.
+    pub fn call_blocked<R: Rng + ?Sized>(&mut self, rng: &mut R, from_orbit: bool) -> Option<f64> {
+        if rng.gen::<f64>() < self.persistence {
+            if !from_orbit {
+                self.size += 1;
+            }
+            return Some(self.delay.sample(rng));
+        }
+
+        if from_orbit {
+            self.size -= 1;
+        }
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_abandoned_calls += 1;
+        }
+        None
+    }
+
+    pub fn statistics_init(&mut self) {
+        match &mut self.statistics {
+            None => self.statistics = Some(OrbitStatisticsRaw::new()),
+            Some(stat) => stat.clear()
+        }
+    }
+
+    pub fn statistics_preview(&self) -> Option<OrbitStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_orbit_statistics())
+    }
+}
@@ -1,7 +1,8 @@
-use std::cmp::Ordering;
-
 use crate::sim::simulator::Simulator;
+use crate::sim::simulator::system::{CallAdmission, StartedCall};
 use crate::sim::model::class::sim_class::SimClass;
+use crate::sim::model::system::PreemptionPolicy;
+use crate::sim::model::batch::BatchPolicy;
 
 //use self::Ordering::*;
 //use std::collections::BinaryHeap;
@@ -15,54 +16,464 @@ use crate::sim::model::class::sim_class::SimClass;
 #[derive(Clone, Copy)]
 pub enum State {
     WaitForNew,
-    WaitForService
+    WaitForService,
+    /// Call waits in the queue, value is call identifier in the queue
+    WaitInQueue(u64),
+    /// Blocked call stays in the retrial orbit
+    WaitForRetrial,
+    /// Working server waits for its failure
+    WaitForFailure,
+    /// Failed server waits for the end of its repair
+    WaitForRepair,
+    /// Call waits for handoff to the neighbouring cell, value is its remaining service time
+    WaitForHandoff(f64),
+    /// Admitted call waits for the setup of sleeping servers, value is the arrival time of the call
+    WaitForSetup(f64),
+    /// Idle server waits for the end of its background setup
+    WaitForServerSetup
 }
 
 #[derive(Clone)]
-pub struct SimProcess<'a>//: Ord
+pub struct SimProcess<'a>
 {
     pub state: State,
     pub time: f64,
+    /// Class of the call. Servers' failure and repair events refer to the first class.
     pub class: &'a SimClass,
+    /// Index of the class in the simulated class mix
+    pub class_idx: usize,
+    /// Event identifier, assigned by the scheduler
+    pub id: u64,
+    /// Alternative group that carries the call, used only with topology of several groups
+    pub alternative: usize,
+    /// Cell that carries the call, used only with handoffs between cells
+    pub cell: usize
+}
+
+/// Call in service that can be preempted
+#[derive(Clone, Copy)]
+pub struct CallInService
+{
+    /// Identifier of the service end event
+    pub id: u64,
+    pub class_idx: usize,
+    pub priority: u32,
+    pub t: usize,
+    /// Time of arrival of the call, before its waiting in the queue or for the setup
+    pub arrival: f64,
+    /// Macrostate found by the call at the start of its service
+    pub state: usize
 }
 
-impl Ord for SimProcess<'_> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.time < other.time {
-            Ordering::Greater
+impl <'a> SimProcess<'a>  {
+    pub fn new(class: &'a SimClass, class_idx: usize) -> SimProcess<'a> {
+        SimProcess::with_state(State::WaitForNew, 0.1f64, class, class_idx)
+    }
+
+    pub fn with_state(state: State, time: f64, class: &'a SimClass, class_idx: usize) -> SimProcess<'a> {
+        SimProcess {state: state, time: time, class: class, class_idx: class_idx, id: 0, alternative: 0, cell: 0}
+    }
+
+    /// Schedules service end of the call that arrived at `arrival`. Preempted call continues its service for `remaining_time`.
+    fn start_service(class: &'a SimClass, class_idx: usize, remaining_time: Option<f64>, arrival: f64, alternative: usize, system: &mut Simulator<'a>) {
+        let time = match remaining_time {
+            Some(time) => time,
+            None if class.get_t_min().is_some() =>
+                class.get_time_end_call(&mut system.rng, &mut system.service_traces[class_idx]) / system.group.compression_ratio(),
+            None => match &system.group.servers {
+                Some(servers) => class.get_time_end_call(&mut system.rng, &mut system.service_traces[class_idx]) / servers.taken_rate(),
+                None => class.get_time_end_call(&mut system.rng, &mut system.service_traces[class_idx]) / system.group.service_rate()
+            }
+        };
+        let mut process = SimProcess::with_state(State::WaitForService, time, class, class_idx);
+        process.alternative = alternative;
+        let id = system.scheduler.add_process(process);
+        if let Some(servers) = &mut system.group.servers {
+            servers.bind(id);
         }
-        else if self.time > other.time {
-            Ordering::Less
+        if let Some(breakdown) = &mut system.group.breakdown {
+            breakdown.bind(id, class.get_t());
         }
-        else {
-            Ordering::Equal
+        if system.tracks_calls_in_service() {
+            system.in_service.push(CallInService {
+                id: id,
+                class_idx: class_idx,
+                priority: class.get_priority(),
+                t: class.get_t(),
+                arrival: arrival,
+                state: system.group.occupancy().saturating_sub(class.get_t())
+            });
         }
     }
-}
 
-impl <'a> SimProcess<'a>  {
-    pub fn new(class: &'a SimClass) -> SimProcess<'a> {
-        SimProcess {state:State::WaitForNew, time: 0.1f64, class:class}
+    /// Schedules handoff of the call if it ends its dwell time in the cell before the end of service,
+    /// otherwise schedules the service end
+    fn stay_in_cell(class: &'a SimClass, class_idx: usize, cell: usize, remaining_time: f64, system: &mut Simulator<'a>) {
+        let topology = system.topology.as_ref().expect("Handoff without topology");
+        let dwell = topology.get_dwell_time(&mut system.rng).unwrap_or(f64::INFINITY);
+        let mut process = if dwell < remaining_time {
+            SimProcess::with_state(State::WaitForHandoff(remaining_time - dwell), dwell, class, class_idx)
+        } else {
+            SimProcess::with_state(State::WaitForService, remaining_time, class, class_idx)
+        };
+        process.cell = cell;
+        system.scheduler.add_process(process);
+    }
+
+    /// Schedules the end of background setups of sleeping servers started by the last call
+    fn start_setups(class: &'a SimClass, class_idx: usize, system: &mut Simulator<'a>) {
+        if let Some(sleep) = &mut system.group.sleep {
+            for _ in 0..sleep.take_pending_setups() {
+                let time = sleep.get_setup_time(&mut system.rng);
+                system.scheduler.add_process(SimProcess::with_state(State::WaitForServerSetup, time, class, class_idx));
+            }
+        }
+    }
+
+    /// Reschedules service ends of elastic calls after the compression ratio has changed.
+    /// Remaining work of the call doesn't change, so its service time stretches or shrinks.
+    fn compression_changed(system: &mut Simulator<'a>, old_ratio: f64) {
+        let ratio = system.group.compression_ratio();
+        if ratio == old_ratio {
+            return;
+        }
+        for idx in 0..system.in_service.len() {
+            let call = system.in_service[idx];
+            if system.tr_classes[call.class_idx].get_t_min().is_none() {
+                continue;
+            }
+            let mut process = system.scheduler.cancel_process(call.id).expect("Elastic call is not in service");
+            process.time *= old_ratio / ratio;
+            system.in_service[idx].id = system.scheduler.add_process(process);
+        }
     }
+
+    /// Rescales remaining service times of calls after the service rate has changed with the macrostate.
+    /// Remaining work of the call doesn't change, so its service time stretches or shrinks.
+    fn service_rate_changed(system: &mut Simulator<'a>, old_rate: f64) {
+        let rate = system.group.service_rate();
+        if rate == old_rate {
+            return;
+        }
+        for idx in 0..system.in_service.len() {
+            let mut process = system.scheduler.cancel_process(system.in_service[idx].id).expect("Call is not in service");
+            process.time *= old_rate / rate;
+            system.in_service[idx].id = system.scheduler.add_process(process);
+        }
+    }
+
+    /// Preempts calls of lower priority if the call doesn't fit into the group.
+    /// Calls of the lowest priority that started their service as the last ones are preempted first.
+    /// Returns true if calls were preempted.
+    fn preempt(&self, system: &mut Simulator<'a>, time_delta: f64) -> bool {
+        let t = self.class.get_t();
+        let priority = self.class.get_priority();
+        let policy = match system.preemption {
+            Some(policy) => policy,
+            None => return false
+        };
+        if system.group.get_v_free() >= t || system.group.has_waiting_calls() {
+            return false;
+        }
+
+        let mut candidates: Vec<CallInService> = system.in_service.iter()
+            .filter(|call| call.priority < priority)
+            .cloned()
+            .collect();
+        candidates.sort_by(|a, b| a.priority.cmp(&b.priority).then(b.id.cmp(&a.id)));
+
+        let mut v_free = system.group.get_v_free();
+        let mut victims = Vec::new();
+        for call in candidates {
+            if v_free >= t {
+                break;
+            }
+            v_free += call.t;
+            victims.push(call);
+        }
+        if v_free < t {
+            return false;
+        }
+
+        let mut time_delta = time_delta;
+        for call in victims {
+            system.in_service.retain(|itm| itm.id != call.id);
+            if let Some(breakdown) = &mut system.group.breakdown {
+                breakdown.call_end(call.id);
+            }
+            let process = system.scheduler.cancel_process(call.id).expect("Preempted call is not in service");
+            let remaining_time = match policy {
+                PreemptionPolicy::Resume => Some(process.time),
+                PreemptionPolicy::Drop => None
+            };
+            system.group.call_preempt(call.class_idx, call.t, call.priority, remaining_time, call.arrival, time_delta);
+            time_delta = 0f64;
+
+            if remaining_time.is_none() {
+                system.total_lost += 1;
+                system.total_serv += 1;
+                SimProcess::source_idle(process.class, process.class_idx, system);
+            }
+        }
+        true
+    }
+
+    /// Offers the call to the group. Starts its service or waiting in the queue.
+    /// Returns false if the call was blocked.
+    fn offer_call(&self, system: &mut Simulator<'a>, time_delta: f64) -> bool {
+        if let Some(topology) = &mut system.topology {
+            let handoff = topology.has_handoff();
+            return match topology.call_add(self.class_idx, self.class.get_t(), &mut system.rng) {
+                Some(_) if handoff => {
+                    let cell = topology.home_cell(self.class_idx);
+                    let time = self.class.get_time_end_call(&mut system.rng, &mut system.service_traces[self.class_idx]);
+                    SimProcess::stay_in_cell(self.class, self.class_idx, cell, time, system);
+                    true
+                }
+                Some(alternative) => {
+                    SimProcess::start_service(self.class, self.class_idx, None, system.time, alternative, system);
+                    true
+                }
+                None => {
+                    system.total_lost += 1;
+                    system.total_serv += 1;
+                    false
+                }
+            };
+        }
+        let time_delta = if self.preempt(system, time_delta) { 0f64 } else { time_delta };
+
+        let old_ratio = system.group.compression_ratio();
+        let old_rate = system.group.service_rate();
+        let admission = system.group.call_add(self.class_idx, self.class.get_t(), time_delta, &mut system.rng);
+        SimProcess::start_setups(self.class, self.class_idx, system);
+        match admission {
+            CallAdmission::Admitted => {
+                SimProcess::compression_changed(system, old_ratio);
+                SimProcess::service_rate_changed(system, old_rate);
+                SimProcess::start_service(self.class, self.class_idx, None, system.time, 0, system);
+                true
+            }
+            CallAdmission::Setup => {
+                let time = system.group.sleep.as_ref().unwrap().get_setup_time(&mut system.rng);
+                system.scheduler.add_process(SimProcess::with_state(State::WaitForSetup(system.time), time, self.class, self.class_idx));
+                true
+            }
+            CallAdmission::Queued(call_id) => {
+                let patience = system.group.queue.as_ref().and_then(|queue| queue.get_patience(&mut system.rng));
+                if let Some(time) = patience {
+                    system.scheduler.add_process(SimProcess::with_state(State::WaitInQueue(call_id), time, self.class, self.class_idx));
+                }
+                true
+            }
+            CallAdmission::Lost => {
+                system.total_lost += 1;
+                system.total_serv += 1;
+                false
+            }
+        }
+    }
+
+    /// Offers calls of the batch one after another. With all-or-nothing policy the whole batch
+    /// is lost when it doesn't fit into free BBUs. Blocked calls move to the retrial orbit separately.
+    fn offer_batch(&self, system: &mut Simulator<'a>, time_delta: f64, policy: BatchPolicy) {
+        let size = self.class.get_batch_size(&mut system.rng);
+        let t = self.class.get_t();
+        let fits = policy == BatchPolicy::Partial || system.group.batch_fits(self.class_idx, size * t);
+
+        let mut time_delta = time_delta;
+        let mut blocked = false;
+        for _ in 0..size {
+            let admitted = if fits {
+                self.offer_call(system, time_delta)
+            }
+            else {
+                system.group.call_reject(self.class_idx, t, time_delta);
+                system.total_lost += 1;
+                system.total_serv += 1;
+                false
+            };
+            time_delta = 0f64;
+            if let Some(orbit) = &mut system.orbit {
+                orbit.primary_call(admitted);
+            }
+            if !admitted {
+                blocked = true;
+                self.call_blocked(system, false);
+            }
+        }
+        system.group.batch_offered(self.class_idx, blocked);
+    }
+
+    /// Moves blocked call to the retrial orbit if the orbit exists and the call doesn't give up.
+    /// Returns true if the call stays in the orbit.
+    fn call_blocked(&self, system: &mut Simulator<'a>, from_orbit: bool) -> bool {
+        let delay = match &mut system.orbit {
+            Some(orbit) => orbit.call_blocked(&mut system.rng, from_orbit),
+            None => None
+        };
+        if let Some(time) = delay {
+            system.scheduler.add_process(SimProcess::with_state(State::WaitForRetrial, time, self.class, self.class_idx));
+            return true;
+        }
+        false
+    }
+
+    /// Starts service of calls taken from the queue or resumed after preemption
+    fn start_admitted(admitted: Vec<StartedCall>, system: &mut Simulator<'a>) {
+        for call in admitted {
+            let class = &system.tr_classes[call.class_idx];
+            let arrival = call.arrival.unwrap_or(system.time - call.waiting_time);
+            SimProcess::start_service(class, call.class_idx, call.remaining_time, arrival, 0, system);
+        }
+    }
+
+    /// Server failed. If it served the call that can't be moved, the call is lost.
+    fn server_failure(system: &mut Simulator<'a>, time_delta: f64) {
+        let call_id = match system.group.server_fail(&mut system.rng, time_delta) {
+            Some(call_id) => call_id,
+            None => return
+        };
+
+        let call_idx = system.in_service.iter()
+            .position(|call| call.id == call_id)
+            .expect("Failed server doesn't serve any call");
+        let call = system.in_service.remove(call_idx);
+        let process = system.scheduler.cancel_process(call.id).expect("Interrupted call is not in service");
+
+        let admitted = system.group.call_interrupt(call.class_idx, call.t);
+        SimProcess::start_admitted(admitted, system);
+        system.total_lost += 1;
+        system.total_serv += 1;
+        SimProcess::source_idle(process.class, process.class_idx, system);
+    }
+
+    /// Call of the finite source class left the system, its source starts thinking again
+    fn source_idle(class: &'a SimClass, class_idx: usize, system: &mut Simulator<'a>) {
+        if class.get_sources().is_some() {
+            let time = class.get_time_new_call_at(&mut system.rng, system.time, &mut system.arrival_states[class_idx]);
+            system.scheduler.add_process(SimProcess::with_state(State::WaitForNew, time, class, class_idx));
+        }
+    }
+
     pub fn execute(mut self, system: &mut Simulator<'a>) -> bool
     {
         let last_time = self.time;
+        system.time += last_time;
+        if let Some(orbit) = &mut system.orbit {
+            orbit.time_update(last_time);
+        }
+        if let Some(topology) = &mut system.topology {
+            topology.time_update(last_time);
+        }
         if match self.state {
+            State::WaitForNew if self.class.tr_class.get_batch().is_some() => {
+                let policy = self.class.tr_class.get_batch().unwrap().policy;
+                self.offer_batch(system, last_time, policy);
+                self.time = self.class.get_time_new_call_at(&mut system.rng, system.time, &mut system.arrival_states[self.class_idx]);
+                true
+            },
             State::WaitForNew => {
-                if system.group.call_add(last_time) {
-                    let time = self.class.get_time_end_call(&mut system.rng);
-                    system.scheduler.add_process(SimProcess { state: State::WaitForService, time: time, class: &self.class });
+                let admitted = self.offer_call(system, last_time);
+                if let Some(orbit) = &mut system.orbit {
+                    orbit.primary_call(admitted);
+                }
+                let in_orbit = !admitted && self.call_blocked(system, false);
+                if self.class.get_sources().is_some() && (admitted || in_orbit) {
+                    // Source is silent as long as its call is in the system
+                    false
                 }
                 else {
+                    self.time = self.class.get_time_new_call_at(&mut system.rng, system.time, &mut system.arrival_states[self.class_idx]);
+                    true
+                }
+            },
+            State::WaitForService => {
+                if system.tracks_calls_in_service() {
+                    if let (Some(sharing), Some(call)) = (&mut system.group.sharing, system.in_service.iter().find(|itm| itm.id == self.id)) {
+                        sharing.call_served(call.state, system.time - call.arrival);
+                    }
+                    system.in_service.retain(|itm| itm.id != self.id);
+                }
+                let admitted = match &mut system.topology {
+                    Some(topology) if topology.has_handoff() => {
+                        topology.call_end_in_cell(self.class_idx, self.class.get_t(), self.cell);
+                        Vec::new()
+                    }
+                    Some(topology) => {
+                        topology.call_end(self.class_idx, self.class.get_t(), self.alternative);
+                        Vec::new()
+                    }
+                    None => {
+                        if let Some(servers) = &mut system.group.servers {
+                            servers.call_end(self.id);
+                        }
+                        if let Some(breakdown) = &mut system.group.breakdown {
+                            breakdown.call_end(self.id);
+                        }
+                        let old_ratio = system.group.compression_ratio();
+                        let old_rate = system.group.service_rate();
+                        let admitted = system.group.call_end(self.class_idx, self.class.get_t(), last_time);
+                        SimProcess::compression_changed(system, old_ratio);
+                        SimProcess::service_rate_changed(system, old_rate);
+                        admitted
+                    }
+                };
+                SimProcess::start_admitted(admitted, system);
+                system.total_serv += 1;
+                SimProcess::source_idle(self.class, self.class_idx, system);
+                false
+            },
+            State::WaitInQueue(call_id) => {
+                if system.group.call_abandon(self.class_idx, call_id, last_time) {
                     system.total_lost += 1;
                     system.total_serv += 1;
+                    SimProcess::source_idle(self.class, self.class_idx, system);
                 }
-                self.time = self.class.get_time_new_call(&mut system.rng);
+                false
+            },
+            State::WaitForRetrial => {
+                let admitted = self.offer_call(system, last_time);
+                if let Some(orbit) = &mut system.orbit {
+                    orbit.repeated_call(admitted);
+                }
+                if !admitted && !self.call_blocked(system, true) {
+                    SimProcess::source_idle(self.class, self.class_idx, system);
+                }
+                false
+            },
+            State::WaitForFailure => {
+                SimProcess::server_failure(system, last_time);
+                self.state = State::WaitForRepair;
+                self.time = system.group.breakdown.as_ref().unwrap().get_repair_time(&mut system.rng);
                 true
             },
-            State::WaitForService => {
-                system.group.call_end(last_time);
-                system.total_serv += 1;
+            State::WaitForRepair => {
+                let admitted = system.group.server_repair(last_time);
+                SimProcess::start_admitted(admitted, system);
+                self.state = State::WaitForFailure;
+                self.time = system.group.breakdown.as_ref().unwrap().get_failure_time(&mut system.rng);
+                true
+            },
+            State::WaitForHandoff(remaining_time) => {
+                let topology = system.topology.as_mut().expect("Handoff without topology");
+                match topology.handoff(self.class_idx, self.class.get_t(), self.cell, &mut system.rng) {
+                    Some(cell) => SimProcess::stay_in_cell(self.class, self.class_idx, cell, remaining_time, system),
+                    None => {
+                        system.total_lost += 1;
+                        system.total_serv += 1;
+                        SimProcess::source_idle(self.class, self.class_idx, system);
+                    }
+                }
+                false
+            },
+            State::WaitForSetup(arrival) => {
+                system.group.time_update(last_time);
+                SimProcess::start_service(self.class, self.class_idx, None, arrival, 0, system);
+                false
+            },
+            State::WaitForServerSetup => {
+                system.group.server_setup(last_time);
                 false
             }
         }
@@ -72,18 +483,3 @@ impl <'a> SimProcess<'a>  {
         true
     }
 }
-
-impl PartialOrd for SimProcess<'_> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
-    }
-}
-
-impl Eq for SimProcess<'_> {
-    fn assert_receiver_is_total_eq(&self) { }
-}
-
-impl PartialEq for SimProcess<'_> {
-    fn eq(&self, other: &Self) -> bool { self.time.eq(&other.time) }
-    fn ne(&self, other: &Self) -> bool { self.time.ne(&other.time) }
-}
\ No newline at end of file
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use rand::Rng;
+
+use crate::sim::model::class::StreamOfEvents;
+use crate::sim::model::system::QueueDescription;
+use crate::sim::simulator::single_statistics::{QueueStatisticsRaw, QueueStatistics};
+
+#[derive(Clone, Copy)]
+pub struct WaitingCall
+{
+    pub id: u64,
+    pub class_idx: usize,
+    /// Number of BBUs demanded by the call
+    pub t: usize,
+    arrival_time: f64
+}
+
+/// Waiting room in front of the group. Calls are served in FIFO order.
+#[derive(Clone)]
+pub struct Queue
+{
+    /// Number of waiting places, None for infinite queue
+    pub capacity: Option<usize>,
+    patience: Option<StreamOfEvents>,
+    calls: VecDeque<WaitingCall>,
+    next_id: u64,
+    time: f64,
+    statistics: Option<QueueStatisticsRaw>
+}
+
+impl Queue {
+    /// Returns None if patience stream parameters are not valid
+    pub fn new(description: &QueueDescription) -> Option<Self> {
+        let patience = match &description.patience {
+            Some(params) => Some(params.get_stream()?),
+            None => None
+        };
+
+        Some(Queue {
+            capacity: description.capacity,
+            patience: patience,
+            calls: VecDeque::new(),
+            next_id: 0,
+            time: 0f64,
+            statistics: None
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        match self.capacity {
+            Some(capacity) => self.calls.len() >= capacity,
+            None => false
+        }
+    }
+
+    /// Time of waiting for service after which the call leaves the queue.
+    /// None if calls never abandon the queue.
+    pub fn get_patience<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<f64> {
+        self.patience.as_ref().map(|stream| stream.sample(rng))
+    }
+
+    pub fn time_update(&mut self, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.update_time(self.calls.len(), time_delta);
+        }
+        self.time += time_delta;
+    }
+
+    /// New call was offered to the system and admitted immediately
+    pub fn call_served_immediately(&mut self) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_calls += 1;
+            stat.call_served(0f64);
+        }
+    }
+
+    /// New call found the group and the queue full
+    pub fn call_rejected(&mut self) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_calls += 1;
+            stat.no_of_rejected_calls += 1;
+        }
+    }
+
+    /// Places new call at the end of the queue and returns its identifier
+    pub fn push(&mut self, class_idx: usize, t: usize) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.calls.push_back(WaitingCall { id: id, class_idx: class_idx, t: t, arrival_time: self.time });
+
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_calls += 1;
+            stat.no_of_waiting_calls += 1;
+        }
+        id
+    }
+
+    pub fn front(&self) -> Option<&WaitingCall> {
+        self.calls.front()
+    }
+
+    /// Time the call has been waiting in the queue
+    pub fn waiting_time(&self, call: &WaitingCall) -> f64 {
+        self.time - call.arrival_time
+    }
+
+    /// Takes the first call from the queue in order to serve it
+    pub fn pop(&mut self) -> Option<WaitingCall> {
+        let call = self.calls.pop_front()?;
+        let waiting_time = self.waiting_time(&call);
+        if let Some(stat) = &mut self.statistics {
+            stat.call_served(waiting_time);
+        }
+        Some(call)
+    }
+
+    /// Removes the call which patience ran out. Returns false if call is no longer waiting.
+    pub fn abandon(&mut self, id: u64) -> bool {
+        match self.calls.iter().position(|call| call.id == id) {
+            Some(idx) => {
+                self.calls.remove(idx);
+                if let Some(stat) = &mut self.statistics {
+                    stat.no_of_abandoned_calls += 1;
+                }
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn statistics_init(&mut self) {
+        match &mut self.statistics {
+            None => self.statistics = Some(QueueStatisticsRaw::new()),
+            Some(stat) => stat.clear()
+        }
+    }
+
+    pub fn statistics_preview(&self) -> Option<QueueStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_queue_statistics())
+    }
+}
@@ -1,25 +1,52 @@
-use std::collections::BinaryHeap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::sim::simulator::process::*;
 
+/// Position of the event in the agenda. Events with the same time are ordered by identifier.
+#[derive(Clone, Copy, PartialEq)]
+struct EventKey
+{
+    time: f64,
+    id: u64
+}
+
+impl Eq for EventKey {}
+
+impl PartialOrd for EventKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EventKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time.total_cmp(&other.time).then(self.id.cmp(&other.id))
+    }
+}
+
 #[derive(Clone)]
 pub struct Scheduler<'a>
 {
     offset: f64,
-    agenda: BinaryHeap<SimProcess<'a>>,
+    next_id: u64,
+    agenda: BTreeMap<EventKey, SimProcess<'a>>,
+    /// Time of every scheduled event, required to cancel the event
+    times: HashMap<u64, f64>,
 }
 
 impl <'a>Scheduler<'a>
 {
     pub fn new() -> Scheduler<'a> {
         Scheduler {
-            offset: 0f64, agenda: BinaryHeap::new()
+            offset: 0f64, next_id: 0, agenda: BTreeMap::new(), times: HashMap::new()
         }
     }
 
     pub fn get_process(&mut self) -> SimProcess<'a>
     {
-        let mut result = self.agenda.pop().unwrap();
+        let (_, mut result) = self.agenda.pop_first().unwrap();
+        self.times.remove(&result.id);
         result.time-= self.offset;
         self.offset+= result.time;
         if self.offset > 1024f64*1024f64 {
@@ -28,14 +55,34 @@ impl <'a>Scheduler<'a>
         result
     }
 
-    pub fn add_process(&mut self, mut event: SimProcess<'a>) {
+    /// Adds process to the agenda, `time` of the process is relative to the current time.
+    /// Returns identifier of the event.
+    pub fn add_process(&mut self, mut event: SimProcess<'a>) -> u64 {
         event.time+= self.offset;
-        self.agenda.push(event)
+        event.id = self.next_id;
+        self.next_id += 1;
+
+        self.times.insert(event.id, event.time);
+        self.agenda.insert(EventKey { time: event.time, id: event.id }, event);
+        self.next_id - 1
+    }
+
+    /// Removes event from the agenda. Returned process `time` is the time left to the event.
+    pub fn cancel_process(&mut self, id: u64) -> Option<SimProcess<'a>> {
+        let time = self.times.remove(&id)?;
+        let mut result = self.agenda.remove(&EventKey { time: time, id: id })?;
+        result.time -= self.offset;
+        Some(result)
     }
 
     fn clear_offset(&mut self) {
-        let new_items : BinaryHeap<SimProcess> = self.agenda.iter().map(|itm|
-            SimProcess {state: itm.state, time: itm.time-self.offset, class:itm.class}).collect();
+        let offset = self.offset;
+        let new_items : BTreeMap<EventKey, SimProcess> = std::mem::take(&mut self.agenda).into_values().map(|itm| {
+            let mut process = itm;
+            process.time -= offset;
+            (EventKey { time: process.time, id: process.id }, process)
+        }).collect();
+        self.times = new_items.values().map(|itm| (itm.id, itm.time)).collect();
         self.offset = 0f64;
         self.agenda = new_items;
     }
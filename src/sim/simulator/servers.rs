@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use rand::Rng;
+use rand::seq::IteratorRandom;
+
+use crate::sim::model::system::{HuntingPolicy, ServersDescription};
+use crate::sim::simulator::single_statistics::{ServersStatisticsRaw, ServersStatistics};
+
+/// Servers (BBUs) of the group with different service rates. Admitted call takes free servers
+/// chosen by the hunting policy, it keeps them until the end of its service.
+#[derive(Clone)]
+pub struct Servers
+{
+    rates: Vec<f64>,
+    hunting: HuntingPolicy,
+    /// Identifier of the call served by every server, None if the server is free
+    busy: Vec<Option<u64>>,
+    /// Free servers in the order of their release, the first one is idle for the longest time
+    idle: VecDeque<usize>,
+    /// Servers taken by the last admitted call, they are bound to the call when its service starts
+    taken: Vec<usize>,
+    /// Busy servers since the last statistics update
+    busy_servers: Vec<bool>,
+    statistics: Option<ServersStatisticsRaw>
+}
+
+impl Servers {
+    /// Returns the reason if the number of rates is not the capacity of the group or any rate is not positive
+    pub fn new(description: &ServersDescription, v: usize) -> Result<Self, String> {
+        if description.rates.len() != v || description.rates.iter().any(|rate| *rate <= 0f64) {
+            return Err(format!("Every of {} servers needs positive service rate, rates: {:?}", v, description.rates));
+        }
+        Ok(Servers {
+            rates: description.rates.clone(),
+            hunting: description.hunting,
+            busy: vec![None; v],
+            idle: (0..v).collect(),
+            taken: Vec::new(),
+            busy_servers: vec![false; v],
+            statistics: None
+        })
+    }
+
+    /// Free server chosen by the hunting policy
+    fn hunt<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        let free = self.idle.iter().copied();
+        let server = match self.hunting {
+            HuntingPolicy::Sequential => free.min(),
+            HuntingPolicy::Random => free.choose(rng),
+            HuntingPolicy::FastestFree => free.max_by(|a, b| self.rates[*a].total_cmp(&self.rates[*b]).then(b.cmp(a))),
+            HuntingPolicy::LongestIdle => free.take(1).next()
+        }.expect("No free server");
+        self.idle.retain(|idx| *idx != server);
+        server
+    }
+
+    /// Admitted call takes `t` free servers
+    pub fn call_add<R: Rng + ?Sized>(&mut self, t: usize, rng: &mut R) {
+        self.taken.clear();
+        for _ in 0..t {
+            let server = self.hunt(rng);
+            self.taken.push(server);
+        }
+        if let Some(stat) = &mut self.statistics {
+            for server in &self.taken {
+                stat.no_of_calls[*server] += 1;
+            }
+        }
+    }
+
+    /// Mean service rate of servers taken by the last admitted call
+    pub fn taken_rate(&self) -> f64 {
+        self.taken.iter().map(|server| self.rates[*server]).sum::<f64>() / self.taken.len() as f64
+    }
+
+    /// Servers taken by the last admitted call serve the call with given identifier
+    pub fn bind(&mut self, call_id: u64) {
+        for server in self.taken.drain(..) {
+            self.busy[server] = Some(call_id);
+        }
+    }
+
+    /// Call with given identifier ended its service and releases its servers
+    pub fn call_end(&mut self, call_id: u64) {
+        for (server, busy) in self.busy.iter_mut().enumerate() {
+            if *busy == Some(call_id) {
+                *busy = None;
+                self.idle.push_back(server);
+            }
+        }
+    }
+
+    /// Accounts time with servers that were busy since the previous update
+    pub fn time_update(&mut self, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.update_time(&self.busy_servers, time_delta);
+        }
+        for server in 0..self.busy.len() {
+            self.busy_servers[server] = self.busy[server].is_some() || self.taken.contains(&server);
+        }
+    }
+
+    pub fn statistics_init(&mut self) {
+        self.statistics = Some(ServersStatisticsRaw::new(self.rates.len()));
+    }
+
+    pub fn statistics_preview(&self) -> Option<ServersStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_servers_statistics())
+    }
+}
@@ -0,0 +1,61 @@
+use crate::sim::model::system::ServiceRateDescription;
+use crate::sim::simulator::single_statistics::{SharingStatisticsRaw, SharingStatistics};
+
+/// Service rate of calls depending on the macrostate of the group, e.g. processor sharing
+#[derive(Clone)]
+pub struct Sharing
+{
+    rate: ServiceRateDescription,
+    /// Number of calls in service
+    calls: usize,
+    statistics: Option<SharingStatisticsRaw>
+}
+
+impl Sharing {
+    /// Returns the reason if rates are not positive
+    pub fn new(description: &ServiceRateDescription) -> Result<Self, String> {
+        if !description.is_valid() {
+            return Err(format!("Wrong service rate {:?}", description));
+        }
+        Ok(Sharing {
+            rate: description.clone(),
+            calls: 0,
+            statistics: None
+        })
+    }
+
+    /// Service rate of every call in the macrostate
+    pub fn rate(&self, state: usize) -> f64 {
+        self.rate.rate(state, self.calls)
+    }
+
+    pub fn call_started(&mut self) {
+        self.calls += 1;
+    }
+
+    pub fn call_ended(&mut self) {
+        self.calls -= 1;
+    }
+
+    /// Call that started its service in the macrostate left the group after the sojourn time,
+    /// measured from its arrival, so waiting for service is included
+    pub fn call_served(&mut self, state: usize, sojourn: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.call_served(state, sojourn);
+        }
+    }
+
+    pub fn time_update(&mut self, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.time_total += time_delta;
+        }
+    }
+
+    pub fn statistics_init(&mut self, v: usize) {
+        self.statistics = Some(SharingStatisticsRaw::new(v));
+    }
+
+    pub fn statistics_preview(&self) -> Option<SharingStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_sharing_statistics())
+    }
+}
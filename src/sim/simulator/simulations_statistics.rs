@@ -5,12 +5,12 @@ use serde::{Deserialize, Serialize};
 use mongodb::bson::{doc, Uuid};
 
 use crate::sim::model::class::{Class};
-use crate::sim::simulator::single_statistics::{StatisticsFinalized, Macrostate};
+use crate::sim::simulator::single_statistics::{StatisticsFinalized, Macrostate, ClassStatistics};
 
 #[derive(Serialize, Deserialize)]
 pub struct SimStatisticsMultiV
 {
-    pub tr_class: Class,
+    pub tr_classes: Vec<Class>,
     pub results: LinkedList<StatisticsMultiSimulations>
 }
 
@@ -24,6 +24,8 @@ pub struct StatisticsMultiSimulations
     pub v: usize,
     pub states_avarage: Vec<Macrostate>,
     pub states_deviation: Vec<Macrostate>,
+    pub classes_avarage: Vec<ClassStatistics>,
+    pub classes_deviation: Vec<ClassStatistics>,
     pub no_of_events_avg: f64,
     pub no_of_events_dev: f64
 }
@@ -31,11 +33,16 @@ pub struct StatisticsMultiSimulations
 impl StatisticsMultiSimulations {
     pub fn statistics_proc(statistics: &LinkedList<StatisticsFinalized>, v:usize) -> Self {
         let no_of_series = statistics.len();
+        let no_of_classes = statistics.iter().map(|x|x.classes.len()).max().unwrap_or(0);
+        // Group with elastic calls has more macrostates than BBUs
+        let no_of_states = statistics.iter().map(|x|x.states.len()).max().unwrap_or(v+1).max(v+1);
         let mut result = StatisticsMultiSimulations {
             uuids: statistics.into_iter().map(|x|x.metadata.uuid).collect(),
             v: v,
-            states_avarage: vec![Macrostate::new(); v+1],
-            states_deviation: vec![Macrostate::new(); v+1],
+            states_avarage: vec![Macrostate::new(); no_of_states],
+            states_deviation: vec![Macrostate::new(); no_of_states],
+            classes_avarage: vec![ClassStatistics::new(no_of_states - 1); no_of_classes],
+            classes_deviation: vec![ClassStatistics::new(no_of_states - 1); no_of_classes],
             no_of_events_avg: 0.0,
             no_of_events_dev: 0.0
         };
@@ -46,6 +53,15 @@ impl StatisticsMultiSimulations {
                 result.states_avarage[idx].out_new += stat_macr.out_new;
                 result.states_avarage[idx].out_end += stat_macr.out_end;
             }
+            for (cls_idx, stat_cls) in stat_ser.classes.iter().enumerate() {
+                let res_cls = &mut result.classes_avarage[cls_idx];
+                for (idx, stat_macr) in stat_cls.states.iter().enumerate() {
+                    res_cls.states[idx].out_new += stat_macr.out_new;
+                    res_cls.states[idx].out_end += stat_macr.out_end;
+                }
+                res_cls.blocking += stat_cls.blocking;
+                res_cls.preemption += stat_cls.preemption;
+            }
             result.no_of_events_avg+= stat_ser.no_of_events as f64;
         }
 
@@ -54,6 +70,14 @@ impl StatisticsMultiSimulations {
             res_st.out_new /= no_of_series as f64;
             res_st.out_end /= no_of_series as f64;
         }
+        for res_cls in &mut result.classes_avarage {
+            for res_st in &mut res_cls.states {
+                res_st.out_new /= no_of_series as f64;
+                res_st.out_end /= no_of_series as f64;
+            }
+            res_cls.blocking /= no_of_series as f64;
+            res_cls.preemption /= no_of_series as f64;
+        }
         result.no_of_events_avg /= no_of_series as f64;
 
         for stat_ser in statistics {
@@ -62,6 +86,16 @@ impl StatisticsMultiSimulations {
                 result.states_deviation[idx].out_new += (result.states_avarage[idx].out_new - stat_macr.out_new).powi(2);
                 result.states_deviation[idx].out_end += (result.states_avarage[idx].out_end - stat_macr.out_end).powi(2);
             }
+            for (cls_idx, stat_cls) in stat_ser.classes.iter().enumerate() {
+                let avg_cls = &result.classes_avarage[cls_idx];
+                let dev_cls = &mut result.classes_deviation[cls_idx];
+                for (idx, stat_macr) in stat_cls.states.iter().enumerate() {
+                    dev_cls.states[idx].out_new += (avg_cls.states[idx].out_new - stat_macr.out_new).powi(2);
+                    dev_cls.states[idx].out_end += (avg_cls.states[idx].out_end - stat_macr.out_end).powi(2);
+                }
+                dev_cls.blocking += (avg_cls.blocking - stat_cls.blocking).powi(2);
+                dev_cls.preemption += (avg_cls.preemption - stat_cls.preemption).powi(2);
+            }
             result.no_of_events_dev += (result.no_of_events_avg - stat_ser.no_of_events as f64).powi(2);
         }
 
@@ -70,24 +104,37 @@ impl StatisticsMultiSimulations {
             res_st.out_new = (res_st.out_new / no_of_series as f64).sqrt();
             res_st.out_end = (res_st.out_end / no_of_series as f64).sqrt();
         }
+        for res_cls in &mut result.classes_deviation {
+            for res_st in &mut res_cls.states {
+                res_st.out_new = (res_st.out_new / no_of_series as f64).sqrt();
+                res_st.out_end = (res_st.out_end / no_of_series as f64).sqrt();
+            }
+            res_cls.blocking = (res_cls.blocking / no_of_series as f64).sqrt();
+            res_cls.preemption = (res_cls.preemption / no_of_series as f64).sqrt();
+        }
         result.no_of_events_dev = (result.no_of_events_dev / no_of_series as f64).sqrt();
         result
     }
 }
 
 impl SimStatisticsMultiV {
-    pub fn new(tr_class :Class) -> SimStatisticsMultiV {
+    pub fn new(tr_classes :Vec<Class>) -> SimStatisticsMultiV {
         SimStatisticsMultiV {
-            tr_class: tr_class,
+            tr_classes: tr_classes,
             results: LinkedList::new()
         }
     }
 
-    pub fn write_header(v_max :u32, output: &mut File)
+    pub fn write_header(v_max :u32, no_of_classes: usize, output: &mut File)
     {
-        output.write_fmt(format_args!("#A\tArrival Id\tArrival desc\tE²/D²\tServ Id\tServ desc\tE²/D²")).
+        output.write_fmt(format_args!("#A\tArrival Id\tArrival desc\tE²/D²\tServ Id\tServ desc\tE²/D²\tt\tN\tPriority")).
             expect("Write header filed");
 
+        for c in 1..no_of_classes {
+            output.write_fmt(format_args!("\tA_{c}\tArrival Id_{c}\tArrival desc_{c}\tE²/D²_{c}\tServ Id_{c}\tServ desc_{c}\tE²/D²_{c}\tt_{c}\tN_{c}\tPriority_{c}")).
+                expect("Write header filed");
+        }
+
         for v in 1..v_max+1 {
             for n in 0..v+1 {
                 output.write_fmt(format_args!("\tp[{}]_{}", n, v)).
@@ -130,6 +177,34 @@ impl SimStatisticsMultiV {
             }
         }
 
+        for c in 0..no_of_classes {
+            for v in 1..v_max+1 {
+                output.write_fmt(format_args!("\tB{}_{}", c, v)).
+                    expect("Write header filed");
+            }
+        }
+
+        for c in 0..no_of_classes {
+            for v in 1..v_max+1 {
+                output.write_fmt(format_args!("\tδ B{}_{}", c, v)).
+                    expect("Write header filed");
+            }
+        }
+
+        for c in 0..no_of_classes {
+            for v in 1..v_max+1 {
+                output.write_fmt(format_args!("\tPr{}_{}", c, v)).
+                    expect("Write header filed");
+            }
+        }
+
+        for c in 0..no_of_classes {
+            for v in 1..v_max+1 {
+                output.write_fmt(format_args!("\tδ Pr{}_{}", c, v)).
+                    expect("Write header filed");
+            }
+        }
+
         output.write_fmt(format_args!("\n")).
             expect("Write header filed");
     }
@@ -140,15 +215,27 @@ impl SimStatisticsMultiV {
         collection.insert_one(self, None)
     }
 
-    pub fn write(& self, output: &mut File)
+    pub fn write(& self, no_of_classes: usize, output: &mut File)
     {
-        SimStatisticsMultiV::write_sim_par(&self.tr_class, output);
+        for (idx, tr_class) in self.tr_classes.iter().enumerate() {
+            if idx > 0 {
+                output.write_fmt(format_args!("\t")).expect("I/O error");
+            }
+            SimStatisticsMultiV::write_sim_par(tr_class, output);
+        }
+        for _ in self.tr_classes.len()..no_of_classes {
+            output.write_fmt(format_args!("\t\t\t\t\t\t\t\t\t\t")).expect("I/O error");
+        }
         self.write_sim_prob(output);
         self.write_new_int(output);
         self.write_end_int(output);
         self.write_sim_prob_dev(output);
         self.write_new_int_dev(output);
         self.write_end_int_dev(output);
+        self.write_class_param(no_of_classes, false, |cls| cls.blocking, output);
+        self.write_class_param(no_of_classes, true, |cls| cls.blocking, output);
+        self.write_class_param(no_of_classes, false, |cls| cls.preemption, output);
+        self.write_class_param(no_of_classes, true, |cls| cls.preemption, output);
         output.write_fmt(format_args!("\n")).expect("I/O error");
     }
 
@@ -298,10 +385,30 @@ impl SimStatisticsMultiV {
         }
     }
 
+    /// Writes single parameter of every class for capacities 1, 2, ..., v
+    fn write_class_param(& self, no_of_classes: usize, deviation: bool, param: fn(&ClassStatistics) -> f64, output: &mut File) {
+        let v_max = self.results.iter().map(|x|x.v).max().unwrap();
+        for c in 0..no_of_classes {
+            for v in 1..v_max+1 {
+                let item = self.results.iter().find(|&x| x.v == v).and_then(|x|
+                    if deviation { x.classes_deviation.get(c) } else { x.classes_avarage.get(c) });
+                match item {
+                    Some(cls) => output.write_fmt(format_args!("\t{}", param(cls))),
+                    None => output.write_fmt(format_args!("\t"))
+                }.expect("write_class_param failed");
+            }
+        }
+    }
+
     fn write_sim_par(tr_class : &Class, output: &mut File) {
-        output.write_fmt(format_args!("{:0.4}\t{}\t{}\t{}\t{}\t{}\t{}", tr_class.get_a(),
+        let sources = match tr_class.get_sources() {
+            Some(sources) => sources.to_string(),
+            None => String::new()
+        };
+        output.write_fmt(format_args!("{:0.4}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}", tr_class.get_a(),
                                       tr_class.get_str_new_id(), tr_class.get_str_new_desc(), tr_class.get_new_e2d2(),
-                                      tr_class.get_str_end_id(), tr_class.get_str_end_desc(), tr_class.get_end_e2d2())).
+                                      tr_class.get_str_end_id(), tr_class.get_str_end_desc(), tr_class.get_end_e2d2(),
+                                      tr_class.get_t(), sources, tr_class.get_priority())).
             expect("Write write_sim_par filed");
     }
 }
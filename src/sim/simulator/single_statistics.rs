@@ -3,17 +3,33 @@ use mongodb::options::FindOptions;
 use mongodb::bson::{Uuid, doc, Document};
 use semver::{BuildMetadata, Prerelease, Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use std::collections::LinkedList;
+use std::collections::{BTreeMap, LinkedList};
 
 use crate::sim::model::system::ModelDescription;
+use crate::sim::model::topology::TopologyDescription;
 
+/// Ratio of counted events to all events, 0 if there were no events at all
+fn ratio(count: u64, total: u64) -> f64 {
+    if total > 0 { count as f64 / total as f64 } else { 0f64 }
+}
 
-#[derive(Clone)]
+
+#[derive(Clone, Copy)]
 pub enum EventType
 {
     NewCall,
     EndCall,
-    LostCall
+    LostCall,
+    /// New call that was placed in the waiting room
+    QueuedCall,
+    /// Call waiting in the queue left it when its patience ran out, it is lost
+    AbandonedCall,
+    /// Call in service was preempted by the call of higher priority
+    PreemptedCall,
+    /// Call in service was interrupted by the failure of its server
+    InterruptedCall,
+    /// Event not related to new call arrival or service end, only time is accounted
+    Other
 }
 
 /// Single simulation statistics, before processing.
@@ -23,10 +39,28 @@ pub enum EventType
 #[derive(Serialize, Deserialize, Clone)]
 pub struct StatisticsRunExperiment
 {
+    /// Macrostates indexed by number of occupied basic bandwidth units (BBU)
     pub states: Vec<MacrostateRaw>,
+    /// Statistics of every traffic class offered to the group
+    pub classes: Vec<ClassStatisticsRaw>,
     pub time_total: f64
 }
 
+/// Single simulation statistics of one traffic class, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ClassStatisticsRaw
+{
+    /// Class events in macrostates indexed by number of occupied BBUs
+    pub states: Vec<ClassMacrostateRaw>,
+    pub no_of_calls: u64,
+    pub no_of_lost_calls: u64,
+    pub no_of_preempted_calls: u64,
+    pub no_of_interrupted_calls: u64,
+    pub no_of_batches: u64,
+    /// Batches with at least one lost call
+    pub no_of_blocked_batches: u64
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct StatisticsFinalizedMetadata
 {
@@ -41,8 +75,25 @@ pub struct StatisticsFinalizedMetadata
 pub struct StatisticsFinalized
 {
     pub states: Vec<Macrostate>,
+    pub classes: Vec<ClassStatistics>,
     pub v : usize,
     pub no_of_events: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueStatistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orbit: Option<OrbitStatistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub availability: Option<AvailabilityStatistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionStatistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<Vec<WindowStatistics>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<EnergyStatistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servers: Option<ServersStatistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sharing: Option<SharingStatistics>,
     pub metadata: StatisticsFinalizedMetadata
 }
 
@@ -85,6 +136,119 @@ impl StatisticsFinalized {
     }
 }
 
+/// Statistics of one traffic class offered to several groups, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RoutedClassStatisticsRaw
+{
+    pub no_of_calls: u64,
+    pub no_of_lost_calls: u64,
+    /// Lost calls that didn't fit into the group used by all alternatives
+    pub no_of_externally_lost_calls: u64,
+    /// Calls carried by every alternative route
+    pub no_of_carried_calls: Vec<u64>,
+    /// Time of the last call that overflowed from the first alternative
+    pub last_overflow: Option<f64>,
+    pub no_of_overflow_intervals: u64,
+    pub overflow_interval_sum: f64,
+    pub overflow_interval_sum2: f64
+}
+
+/// Processed statistics of one traffic class offered to several groups
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RoutedClassStatistics
+{
+    /// Ratio of calls rejected by all alternatives to all offered calls of the class
+    pub blocking: f64,
+    /// Ratio of calls lost because the group used by all alternatives (e.g. inlet or outlet of the
+    /// switching network) had not enough free BBUs, to all offered calls of the class
+    #[serde(default)]
+    pub external_blocking: f64,
+    /// Ratio of calls lost although groups used by all alternatives had enough free BBUs
+    #[serde(default)]
+    pub internal_blocking: f64,
+    /// Ratio of calls carried by every alternative route to all offered calls of the class
+    pub carried: Vec<f64>,
+    /// Intensity of calls that overflowed from the first alternative, 0 if less than two calls overflowed
+    pub overflow_intensity: f64,
+    /// E²/D² of time between calls that overflowed from the first alternative, 0 if it can't be estimated
+    pub overflow_e2d2: f64
+}
+
+/// Statistics of one cell with handoffs, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CellStatisticsRaw
+{
+    /// New calls of classes offered to the cell
+    pub no_of_new_calls: u64,
+    pub no_of_blocked_new_calls: u64,
+    /// Calls that handed off to the cell from its neighbours
+    pub no_of_handoffs: u64,
+    pub no_of_dropped_handoffs: u64
+}
+
+/// Processed statistics of one cell with handoffs
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CellStatistics
+{
+    /// Ratio of blocked to all new calls offered to the cell
+    pub new_call_blocking: f64,
+    /// Ratio of dropped calls to all calls that handed off to the cell
+    pub handoff_dropping: f64
+}
+
+/// Time spent in joint occupancy states of all groups, before processing.
+#[derive(Clone)]
+pub struct JointStatisticsRaw
+{
+    /// Time spent in every visited joint state, state is the occupancy of every group
+    pub durations: BTreeMap<Vec<usize>, f64>,
+    pub time_total: f64
+}
+
+/// Probability of the joint occupancy state of all groups
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JointState
+{
+    /// Occupancy of every group
+    pub occupancy: Vec<usize>,
+    pub p: f64
+}
+
+/// Processed statistics of the multi-group simulation
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TopologyStatistics
+{
+    /// Statistics of every group, classes are indexed as in the topology description
+    pub groups: Vec<StatisticsFinalized>,
+    /// Statistics of the total occupancy of all groups
+    pub total: StatisticsFinalized,
+    pub classes: Vec<RoutedClassStatistics>,
+    /// Statistics of every cell, None without handoffs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cells: Option<Vec<CellStatistics>>,
+    /// Joint occupancy distribution of all groups (resources), None if only marginal distributions are collected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub joint: Option<Vec<JointState>>,
+    pub no_of_events: u64,
+    pub metadata: StatisticsFinalizedMetadata
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TopologyStatisticsWithInputModel {
+    system: TopologyDescription,
+    stat: TopologyStatistics
+}
+
+impl TopologyStatistics {
+    pub fn write_mongo(& self, topology: &TopologyDescription, db: &mut mongodb::sync::Database) -> Result<mongodb::results::InsertOneResult, mongodb::error::Error>
+    {
+        let data = TopologyStatisticsWithInputModel {stat: self.clone(), system: topology.clone()};
+
+        let collection = db.collection::<TopologyStatisticsWithInputModel>("topology_statistics");
+        collection.insert_one(data, None)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct MacrostateRaw
 {
@@ -101,6 +265,254 @@ pub struct Macrostate
     pub out_end: f64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ClassMacrostateRaw
+{
+    pub no_out_new: usize,
+    pub no_out_end: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ClassMacrostate
+{
+    pub out_new: f64,
+    pub out_end: f64,
+}
+
+/// Waiting room statistics of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QueueStatisticsRaw
+{
+    /// Time spent with given number of calls in the queue
+    pub length_durations: Vec<f64>,
+    pub time_total: f64,
+    pub no_of_calls: u64,
+    pub no_of_waiting_calls: u64,
+    pub no_of_abandoned_calls: u64,
+    /// Calls that found the group and the queue full
+    pub no_of_rejected_calls: u64,
+    pub no_of_served_calls: u64,
+    /// Sum of waiting times of served calls, calls served immediately have waiting time 0
+    pub waiting_time_sum: f64,
+    pub waiting_time_sum2: f64
+}
+
+/// Processed waiting room statistics
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QueueStatistics
+{
+    pub waiting_time_mean: f64,
+    pub waiting_time_variance: f64,
+    /// Probability that new call has to wait
+    pub p_wait: f64,
+    /// Probability that new call leaves the queue when its patience runs out
+    pub p_abandon: f64,
+    /// Probability that new call finds the queue full
+    pub p_rejected: f64,
+    /// Probability of given number of calls in the queue
+    pub length_distribution: Vec<f64>
+}
+
+/// Retrial orbit statistics of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OrbitStatisticsRaw
+{
+    /// Time spent with given number of calls in the orbit
+    pub size_durations: Vec<f64>,
+    pub time_total: f64,
+    pub no_of_primary_calls: u64,
+    pub no_of_primary_calls_blocked: u64,
+    pub no_of_repeated_calls: u64,
+    pub no_of_repeated_calls_blocked: u64,
+    pub no_of_successful_calls: u64,
+    /// Blocked calls that gave up retrying
+    pub no_of_abandoned_calls: u64
+}
+
+/// Processed retrial orbit statistics
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OrbitStatistics
+{
+    /// Probability of given number of calls in the orbit
+    pub size_distribution: Vec<f64>,
+    pub retrials_per_successful_call: f64,
+    /// Congestion of first attempts
+    pub primary_congestion: f64,
+    /// Congestion of attempts from the orbit
+    pub repeated_congestion: f64,
+    /// Probability that new call finally leaves the system without service
+    pub p_abandon: f64
+}
+
+/// Servers' failures statistics of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AvailabilityStatisticsRaw
+{
+    /// Time spent in states indexed by number of failed and number of busy BBUs
+    pub durations: Vec<Vec<f64>>,
+    pub time_total: f64,
+    pub no_of_calls: u64,
+    /// Calls that would be blocked even if all servers worked
+    pub no_of_blocked_congestion: u64,
+    /// Calls that would be admitted if all servers worked
+    pub no_of_blocked_capacity: u64,
+    pub no_of_failures: u64,
+    pub no_of_interrupted_calls: u64
+}
+
+/// Processed servers' failures statistics
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AvailabilityStatistics
+{
+    /// Probability of given number of failed (first index) and busy (second index) BBUs
+    pub distribution: Vec<Vec<f64>>,
+    /// Probability of given number of failed BBUs
+    pub failed_distribution: Vec<f64>,
+    /// Ratio of calls blocked due to congestion to all offered calls
+    pub blocking_congestion: f64,
+    /// Ratio of calls blocked due to reduced capacity to all offered calls
+    pub blocking_capacity: f64,
+    /// Ratio of calls interrupted by server failure to all offered calls
+    pub p_interrupted: f64
+}
+
+/// Elastic calls' statistics of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CompressionStatisticsRaw
+{
+    /// Time spent in macrostates indexed by nominal number of BBUs demanded by calls
+    pub durations: Vec<f64>,
+    /// Compression ratio integrated over time spent in the macrostate
+    pub compression: Vec<f64>,
+    /// Number of carried BBUs integrated over time spent in the macrostate
+    pub throughput: Vec<f64>
+}
+
+/// Processed elastic calls' statistics
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CompressionStatistics
+{
+    /// Mean ratio of allocated to nominal bandwidth of elastic calls in every macrostate
+    pub compression: Vec<f64>,
+    /// Mean number of carried BBUs in every macrostate
+    pub throughput: Vec<f64>
+}
+
+/// Sleeping servers' statistics of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EnergyStatisticsRaw
+{
+    /// Time spent with given number of awake servers
+    pub durations: Vec<f64>,
+    pub time_total: f64,
+    pub no_of_calls: u64,
+    /// Calls blocked although enough servers were free, but sleeping
+    pub no_of_blocked_sleeping: u64,
+    /// Number of servers woken up
+    pub no_of_setups: u64
+}
+
+/// Processed sleeping servers' statistics
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EnergyStatistics
+{
+    /// Probability of given number of awake servers
+    pub awake_distribution: Vec<f64>,
+    pub mean_awake: f64,
+    /// Number of server setups per unit of time
+    pub setup_rate: f64,
+    /// Ratio of calls blocked due to sleeping servers to all offered calls
+    pub blocking_sleeping: f64
+}
+
+/// Heterogeneous servers' statistics of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServersStatisticsRaw
+{
+    /// Time when every server was busy
+    pub busy_time: Vec<f64>,
+    pub time_total: f64,
+    /// Calls served by every server
+    pub no_of_calls: Vec<u64>
+}
+
+/// Processed heterogeneous servers' statistics, servers are in the order of the description
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServersStatistics
+{
+    /// Traffic carried by every server (probability that the server is busy)
+    pub carried: Vec<f64>,
+    /// Share of admitted calls taken by every server, call demanding several servers counts for each of them
+    pub calls: Vec<f64>
+}
+
+/// Statistics of state-dependent service of single simulation, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SharingStatisticsRaw
+{
+    /// Sum of sojourn times of calls that started their service in given macrostate
+    pub sojourn_sum: Vec<f64>,
+    /// Served calls that started their service in given macrostate
+    pub no_of_calls: Vec<u64>,
+    pub time_total: f64
+}
+
+/// Processed statistics of state-dependent service
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SharingStatistics
+{
+    /// Mean sojourn time of calls that found given macrostate at the start of their service
+    pub sojourn: Vec<f64>,
+    pub mean_sojourn: f64,
+    /// Number of served calls per unit of time
+    pub throughput: f64
+}
+
+/// Statistics of single time window, before processing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WindowStatisticsRaw
+{
+    /// Time spent in occupancy states within the window
+    pub durations: Vec<f64>,
+    pub time_total: f64,
+    /// Number of calls of every class offered within the window
+    pub no_of_calls: Vec<u64>,
+    pub no_of_lost_calls: Vec<u64>
+}
+
+/// Processed statistics of single time window
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WindowStatistics
+{
+    /// Start time of the window (within the period if windows repeat)
+    pub start: f64,
+    /// Duration of the window, it is longer than in the description if windows were merged
+    #[serde(default)]
+    pub length: f64,
+    /// Probability of occupancy states within the window
+    pub states: Vec<f64>,
+    /// Blocking probability of every class within the window
+    pub blocking: Vec<f64>
+}
+
+/// Processed statistics of one traffic class
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ClassStatistics
+{
+    pub states: Vec<ClassMacrostate>,
+    /// Ratio of lost calls to all offered calls of the class
+    pub blocking: f64,
+    /// Ratio of preempted calls to all offered calls of the class
+    #[serde(default)]
+    pub preemption: f64,
+    /// Ratio of calls interrupted by server failure to all offered calls of the class
+    #[serde(default)]
+    pub interruption: f64,
+    /// Ratio of batches with at least one lost call to all offered batches, None without batch arrivals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_blocking: Option<f64>
+}
+
 impl MacrostateRaw {
     pub fn new() -> Self {
         Self { duration:0f64, no_out_new: 0, no_out_end: 0 }
@@ -121,10 +533,72 @@ impl MacrostateRaw {
     }
 }
 
+impl ClassMacrostateRaw {
+    pub fn new() -> Self {
+        Self { no_out_new: 0, no_out_end: 0 }
+    }
+
+    pub fn get_class_macrostate_statistics(&self, duration: f64) -> ClassMacrostate {
+        ClassMacrostate {
+            out_new: self.no_out_new as f64 / duration,
+            out_end: self.no_out_end as f64 / duration
+        }
+    }
+}
+
+impl ClassStatisticsRaw {
+    pub fn new(v: usize) -> Self {
+        Self {
+            states: vec![ClassMacrostateRaw::new(); v+1],
+            no_of_calls: 0,
+            no_of_lost_calls: 0,
+            no_of_preempted_calls: 0,
+            no_of_interrupted_calls: 0,
+            no_of_batches: 0,
+            no_of_blocked_batches: 0
+        }
+    }
+
+    pub fn clear_statistics(&mut self) {
+        for itm in &mut self.states {
+            itm.no_out_new = 0;
+            itm.no_out_end = 0;
+        }
+        self.no_of_calls = 0;
+        self.no_of_lost_calls = 0;
+        self.no_of_preempted_calls = 0;
+        self.no_of_interrupted_calls = 0;
+        self.no_of_batches = 0;
+        self.no_of_blocked_batches = 0;
+    }
+
+    pub fn batch_offered(&mut self, blocked: bool) {
+        self.no_of_batches += 1;
+        if blocked {
+            self.no_of_blocked_batches += 1;
+        }
+    }
+
+    pub fn get_class_statistics(&self, states: &Vec<MacrostateRaw>) -> ClassStatistics {
+        ClassStatistics {
+            states: self.states.iter().zip(states.iter()).map(
+                |(class_state, state)| class_state.get_class_macrostate_statistics(state.duration)
+            ).collect(),
+            blocking: ratio(self.no_of_lost_calls, self.no_of_calls),
+            preemption: ratio(self.no_of_preempted_calls, self.no_of_calls),
+            interruption: ratio(self.no_of_interrupted_calls, self.no_of_calls),
+            batch_blocking: if self.no_of_batches > 0 {
+                Some(self.no_of_blocked_batches as f64 / self.no_of_batches as f64)
+            } else { None }
+        }
+    }
+}
+
 impl StatisticsRunExperiment {
-    pub fn new(v: usize) ->Self {
+    pub fn new(v: usize, no_of_classes: usize) ->Self {
         StatisticsRunExperiment {
             states : vec![MacrostateRaw::new(); v+1],
+            classes: vec![ClassStatisticsRaw::new(v); no_of_classes],
             time_total:0_f64
         }
     }
@@ -134,22 +608,437 @@ impl StatisticsRunExperiment {
         for ref mut itm in &mut self.states {
             itm.clear_statistics();
         }
+        for itm in &mut self.classes {
+            itm.clear_statistics();
+        }
         self.time_total = 0f64;
     }
 
-    pub fn update(&mut self, event_type: EventType, old_state:usize, _new_state:usize, time:f64) {
+    pub fn update(&mut self, event_type: EventType, class_idx: usize, old_state:usize, _new_state:usize, time:f64) {
         self.time_total += time;
         self.states[old_state].duration += time;
+        let class = &mut self.classes[class_idx];
         match event_type {
             EventType::NewCall => {
                 self.states[old_state].no_out_new +=1;
+                class.states[old_state].no_out_new +=1;
+                class.no_of_calls += 1;
             }
             EventType::LostCall => {
                 self.states[old_state].no_out_new +=1;
+                class.states[old_state].no_out_new +=1;
+                class.no_of_calls += 1;
+                class.no_of_lost_calls += 1;
+            }
+            EventType::QueuedCall => {
+                self.states[old_state].no_out_new +=1;
+                class.states[old_state].no_out_new +=1;
+                class.no_of_calls += 1;
+            }
+            EventType::AbandonedCall => {
+                class.no_of_lost_calls += 1;
             }
             EventType::EndCall => {
                 self.states[old_state].no_out_end +=1;
+                class.states[old_state].no_out_end +=1;
             }
+            EventType::PreemptedCall => {
+                class.no_of_preempted_calls += 1;
+            }
+            EventType::InterruptedCall => {
+                class.no_of_interrupted_calls += 1;
+            }
+            EventType::Other => {}
+        }
+    }
+}
+
+impl OrbitStatisticsRaw {
+    pub fn new() -> Self {
+        OrbitStatisticsRaw {
+            size_durations: vec![0f64],
+            time_total: 0f64,
+            no_of_primary_calls: 0,
+            no_of_primary_calls_blocked: 0,
+            no_of_repeated_calls: 0,
+            no_of_repeated_calls_blocked: 0,
+            no_of_successful_calls: 0,
+            no_of_abandoned_calls: 0
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = OrbitStatisticsRaw::new();
+    }
+
+    pub fn update_time(&mut self, orbit_size: usize, time: f64) {
+        if self.size_durations.len() <= orbit_size {
+            self.size_durations.resize(orbit_size + 1, 0f64);
+        }
+        self.size_durations[orbit_size] += time;
+        self.time_total += time;
+    }
+
+    pub fn get_orbit_statistics(&self) -> OrbitStatistics {
+        OrbitStatistics {
+            size_distribution: self.size_durations.iter().map(|x| x / self.time_total).collect(),
+            retrials_per_successful_call: ratio(self.no_of_repeated_calls, self.no_of_successful_calls),
+            primary_congestion: ratio(self.no_of_primary_calls_blocked, self.no_of_primary_calls),
+            repeated_congestion: ratio(self.no_of_repeated_calls_blocked, self.no_of_repeated_calls),
+            p_abandon: ratio(self.no_of_abandoned_calls, self.no_of_primary_calls)
+        }
+    }
+}
+
+impl RoutedClassStatisticsRaw {
+    pub fn new(no_of_alternatives: usize) -> Self {
+        RoutedClassStatisticsRaw {
+            no_of_calls: 0,
+            no_of_lost_calls: 0,
+            no_of_externally_lost_calls: 0,
+            no_of_carried_calls: vec![0; no_of_alternatives],
+            last_overflow: None,
+            no_of_overflow_intervals: 0,
+            overflow_interval_sum: 0f64,
+            overflow_interval_sum2: 0f64
+        }
+    }
+
+    /// Call was carried by given alternative route, None if the call was lost.
+    /// Lost call is `external` if it didn't fit into the group used by all alternatives.
+    /// `time` is used to measure intervals between calls that overflowed from the first alternative.
+    pub fn call_offered(&mut self, alternative: Option<usize>, external: bool, time: f64) {
+        self.no_of_calls += 1;
+        match alternative {
+            Some(idx) => self.no_of_carried_calls[idx] += 1,
+            None if external => {
+                self.no_of_lost_calls += 1;
+                self.no_of_externally_lost_calls += 1;
+            }
+            None => self.no_of_lost_calls += 1
+        }
+        if alternative != Some(0) {
+            if let Some(last) = self.last_overflow {
+                let interval = time - last;
+                self.no_of_overflow_intervals += 1;
+                self.overflow_interval_sum += interval;
+                self.overflow_interval_sum2 += interval * interval;
+            }
+            self.last_overflow = Some(time);
+        }
+    }
+
+    pub fn get_class_statistics(&self) -> RoutedClassStatistics {
+        // Intervals are measured between overflowing calls, there are none with less than two of them
+        let (overflow_intensity, overflow_e2d2) = match self.no_of_overflow_intervals {
+            0 => (0f64, 0f64),
+            n => {
+                let mean = self.overflow_interval_sum / n as f64;
+                let variance = self.overflow_interval_sum2 / n as f64 - mean * mean;
+                (1f64 / mean, if n > 1 && variance > 0f64 { mean * mean / variance } else { 0f64 })
+            }
+        };
+        RoutedClassStatistics {
+            blocking: ratio(self.no_of_lost_calls, self.no_of_calls),
+            external_blocking: ratio(self.no_of_externally_lost_calls, self.no_of_calls),
+            internal_blocking: ratio(self.no_of_lost_calls - self.no_of_externally_lost_calls, self.no_of_calls),
+            carried: self.no_of_carried_calls.iter().map(|x| ratio(*x, self.no_of_calls)).collect(),
+            overflow_intensity: overflow_intensity,
+            overflow_e2d2: overflow_e2d2
+        }
+    }
+}
+
+impl JointStatisticsRaw {
+    pub fn new() -> Self {
+        JointStatisticsRaw {
+            durations: BTreeMap::new(),
+            time_total: 0f64
+        }
+    }
+
+    pub fn update_time(&mut self, state: &[usize], time: f64) {
+        match self.durations.get_mut(state) {
+            Some(duration) => *duration += time,
+            None => { self.durations.insert(state.to_vec(), time); }
+        }
+        self.time_total += time;
+    }
+
+    /// Visited states in lexicographic order of occupancies
+    pub fn get_joint_statistics(&self) -> Vec<JointState> {
+        self.durations.iter()
+            .map(|(occupancy, duration)| JointState { occupancy: occupancy.clone(), p: duration / self.time_total })
+            .collect()
+    }
+}
+
+impl CellStatisticsRaw {
+    pub fn new() -> Self {
+        CellStatisticsRaw {
+            no_of_new_calls: 0,
+            no_of_blocked_new_calls: 0,
+            no_of_handoffs: 0,
+            no_of_dropped_handoffs: 0
+        }
+    }
+
+    pub fn new_call(&mut self, blocked: bool) {
+        self.no_of_new_calls += 1;
+        if blocked {
+            self.no_of_blocked_new_calls += 1;
+        }
+    }
+
+    pub fn handoff(&mut self, dropped: bool) {
+        self.no_of_handoffs += 1;
+        if dropped {
+            self.no_of_dropped_handoffs += 1;
+        }
+    }
+
+    pub fn get_cell_statistics(&self) -> CellStatistics {
+        CellStatistics {
+            new_call_blocking: ratio(self.no_of_blocked_new_calls, self.no_of_new_calls),
+            handoff_dropping: ratio(self.no_of_dropped_handoffs, self.no_of_handoffs)
+        }
+    }
+}
+
+impl AvailabilityStatisticsRaw {
+    pub fn new(v: usize) -> Self {
+        AvailabilityStatisticsRaw {
+            durations: vec![vec![0f64; v + 1]; v + 1],
+            time_total: 0f64,
+            no_of_calls: 0,
+            no_of_blocked_congestion: 0,
+            no_of_blocked_capacity: 0,
+            no_of_failures: 0,
+            no_of_interrupted_calls: 0
+        }
+    }
+
+    pub fn update_time(&mut self, failed: usize, busy: usize, time: f64) {
+        self.durations[failed][busy] += time;
+        self.time_total += time;
+    }
+
+    pub fn get_availability_statistics(&self) -> AvailabilityStatistics {
+        AvailabilityStatistics {
+            distribution: self.durations.iter()
+                .map(|row| row.iter().map(|x| x / self.time_total).collect())
+                .collect(),
+            failed_distribution: self.durations.iter()
+                .map(|row| row.iter().sum::<f64>() / self.time_total)
+                .collect(),
+            blocking_congestion: ratio(self.no_of_blocked_congestion, self.no_of_calls),
+            blocking_capacity: ratio(self.no_of_blocked_capacity, self.no_of_calls),
+            p_interrupted: ratio(self.no_of_interrupted_calls, self.no_of_calls)
+        }
+    }
+}
+
+impl CompressionStatisticsRaw {
+    pub fn new(no_of_states: usize) -> Self {
+        CompressionStatisticsRaw {
+            durations: vec![0f64; no_of_states],
+            compression: vec![0f64; no_of_states],
+            throughput: vec![0f64; no_of_states]
+        }
+    }
+
+    pub fn update_time(&mut self, state: usize, compression: f64, throughput: f64, time: f64) {
+        self.durations[state] += time;
+        self.compression[state] += compression * time;
+        self.throughput[state] += throughput * time;
+    }
+
+    pub fn get_compression_statistics(&self) -> CompressionStatistics {
+        CompressionStatistics {
+            compression: self.compression.iter().zip(self.durations.iter()).map(|(x, time)| x / time).collect(),
+            throughput: self.throughput.iter().zip(self.durations.iter()).map(|(x, time)| x / time).collect()
+        }
+    }
+}
+
+impl EnergyStatisticsRaw {
+    pub fn new(v: usize) -> Self {
+        EnergyStatisticsRaw {
+            durations: vec![0f64; v + 1],
+            time_total: 0f64,
+            no_of_calls: 0,
+            no_of_blocked_sleeping: 0,
+            no_of_setups: 0
+        }
+    }
+
+    pub fn update_time(&mut self, awake: usize, time: f64) {
+        self.durations[awake] += time;
+        self.time_total += time;
+    }
+
+    pub fn get_energy_statistics(&self) -> EnergyStatistics {
+        EnergyStatistics {
+            awake_distribution: self.durations.iter().map(|x| x / self.time_total).collect(),
+            mean_awake: self.durations.iter().enumerate().map(|(awake, x)| awake as f64 * x).sum::<f64>() / self.time_total,
+            setup_rate: self.no_of_setups as f64 / self.time_total,
+            blocking_sleeping: ratio(self.no_of_blocked_sleeping, self.no_of_calls)
+        }
+    }
+}
+
+impl ServersStatisticsRaw {
+    pub fn new(v: usize) -> Self {
+        ServersStatisticsRaw {
+            busy_time: vec![0f64; v],
+            time_total: 0f64,
+            no_of_calls: vec![0; v]
+        }
+    }
+
+    pub fn update_time(&mut self, busy: &[bool], time: f64) {
+        for (busy_time, _) in self.busy_time.iter_mut().zip(busy.iter()).filter(|(_, busy)| **busy) {
+            *busy_time += time;
+        }
+        self.time_total += time;
+    }
+
+    pub fn get_servers_statistics(&self) -> ServersStatistics {
+        let no_of_calls: u64 = self.no_of_calls.iter().sum();
+        ServersStatistics {
+            carried: self.busy_time.iter().map(|x| x / self.time_total).collect(),
+            calls: self.no_of_calls.iter().map(|x| ratio(*x, no_of_calls)).collect()
+        }
+    }
+}
+
+impl SharingStatisticsRaw {
+    pub fn new(v: usize) -> Self {
+        SharingStatisticsRaw {
+            sojourn_sum: vec![0f64; v + 1],
+            no_of_calls: vec![0; v + 1],
+            time_total: 0f64
+        }
+    }
+
+    pub fn call_served(&mut self, state: usize, sojourn: f64) {
+        self.sojourn_sum[state] += sojourn;
+        self.no_of_calls[state] += 1;
+    }
+
+    pub fn get_sharing_statistics(&self) -> SharingStatistics {
+        let no_of_calls: u64 = self.no_of_calls.iter().sum();
+        SharingStatistics {
+            sojourn: self.sojourn_sum.iter().zip(self.no_of_calls.iter())
+                .map(|(sum, n)| if *n > 0 { sum / *n as f64 } else { 0f64 })
+                .collect(),
+            mean_sojourn: if no_of_calls > 0 { self.sojourn_sum.iter().sum::<f64>() / no_of_calls as f64 } else { 0f64 },
+            throughput: no_of_calls as f64 / self.time_total
+        }
+    }
+}
+
+impl WindowStatisticsRaw {
+    pub fn new(no_of_states: usize, no_of_classes: usize) -> Self {
+        WindowStatisticsRaw {
+            durations: vec![0f64; no_of_states],
+            time_total: 0f64,
+            no_of_calls: vec![0; no_of_classes],
+            no_of_lost_calls: vec![0; no_of_classes]
+        }
+    }
+
+    pub fn update_time(&mut self, state: usize, time: f64) {
+        self.durations[state] += time;
+        self.time_total += time;
+    }
+
+    pub fn update(&mut self, event_type: EventType, class_idx: usize) {
+        match event_type {
+            EventType::NewCall | EventType::QueuedCall => self.no_of_calls[class_idx] += 1,
+            EventType::LostCall => {
+                self.no_of_calls[class_idx] += 1;
+                self.no_of_lost_calls[class_idx] += 1;
+            }
+            EventType::AbandonedCall => self.no_of_lost_calls[class_idx] += 1,
+            _ => {}
+        }
+    }
+
+    /// Adds statistics of the following window
+    pub fn merge(&mut self, other: &WindowStatisticsRaw) {
+        for (duration, other) in self.durations.iter_mut().zip(other.durations.iter()) {
+            *duration += other;
+        }
+        self.time_total += other.time_total;
+        for (calls, other) in self.no_of_calls.iter_mut().zip(other.no_of_calls.iter()) {
+            *calls += other;
+        }
+        for (lost, other) in self.no_of_lost_calls.iter_mut().zip(other.no_of_lost_calls.iter()) {
+            *lost += other;
+        }
+    }
+
+    pub fn get_window_statistics(&self, start: f64, length: f64) -> WindowStatistics {
+        WindowStatistics {
+            start: start,
+            length: length,
+            states: self.durations.iter().map(|x| x / self.time_total).collect(),
+            blocking: self.no_of_lost_calls.iter().zip(self.no_of_calls.iter())
+                .map(|(lost, calls)| ratio(*lost, *calls))
+                .collect()
+        }
+    }
+}
+
+impl QueueStatisticsRaw {
+    pub fn new() -> Self {
+        QueueStatisticsRaw {
+            length_durations: vec![0f64],
+            time_total: 0f64,
+            no_of_calls: 0,
+            no_of_waiting_calls: 0,
+            no_of_abandoned_calls: 0,
+            no_of_rejected_calls: 0,
+            no_of_served_calls: 0,
+            waiting_time_sum: 0f64,
+            waiting_time_sum2: 0f64
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = QueueStatisticsRaw::new();
+    }
+
+    pub fn update_time(&mut self, queue_len: usize, time: f64) {
+        if self.length_durations.len() <= queue_len {
+            self.length_durations.resize(queue_len + 1, 0f64);
+        }
+        self.length_durations[queue_len] += time;
+        self.time_total += time;
+    }
+
+    pub fn call_served(&mut self, waiting_time: f64) {
+        self.no_of_served_calls += 1;
+        self.waiting_time_sum += waiting_time;
+        self.waiting_time_sum2 += waiting_time * waiting_time;
+    }
+
+    pub fn get_queue_statistics(&self) -> QueueStatistics {
+        let (waiting_time_mean, waiting_time_variance) = match self.no_of_served_calls {
+            0 => (0f64, 0f64),
+            n => {
+                let mean = self.waiting_time_sum / n as f64;
+                (mean, self.waiting_time_sum2 / n as f64 - mean * mean)
+            }
+        };
+        QueueStatistics {
+            waiting_time_mean: waiting_time_mean,
+            waiting_time_variance: waiting_time_variance,
+            p_wait: ratio(self.no_of_waiting_calls, self.no_of_calls),
+            p_abandon: ratio(self.no_of_abandoned_calls, self.no_of_calls),
+            p_rejected: ratio(self.no_of_rejected_calls, self.no_of_calls),
+            length_distribution: self.length_durations.iter().map(|x| x / self.time_total).collect()
         }
     }
 }
@@ -162,4 +1051,25 @@ impl Macrostate {
             out_end: 0f64,
         }
     }
+}
+
+impl ClassMacrostate {
+    pub fn new() -> Self {
+        ClassMacrostate {
+            out_new: 0f64,
+            out_end: 0f64,
+        }
+    }
+}
+
+impl ClassStatistics {
+    pub fn new(v: usize) -> Self {
+        ClassStatistics {
+            states: vec![ClassMacrostate::new(); v+1],
+            blocking: 0f64,
+            preemption: 0f64,
+            interruption: 0f64,
+            batch_blocking: None
+        }
+    }
 }
\ No newline at end of file
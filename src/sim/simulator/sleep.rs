@@ -0,0 +1,136 @@
+use rand::Rng;
+
+use crate::sim::model::class::StreamOfEvents;
+use crate::sim::model::system::{SleepDescription, SleepPolicy};
+use crate::sim::simulator::single_statistics::{EnergyStatisticsRaw, EnergyStatistics};
+
+/// Sleeping servers (BBUs) of the group. Servers that serve calls or are in setup are awake.
+/// Group keeps the reserve of idle awake servers, sleeping servers start their setup in the background
+/// when the reserve gets smaller. Idle servers above the reserve go to sleep immediately.
+/// Call takes awake idle servers first, sleeping servers it takes need setup before the service starts.
+#[derive(Clone)]
+pub struct Sleep
+{
+    setup: StreamOfEvents,
+    /// Number of idle servers that stay awake
+    awake: usize,
+    pub policy: SleepPolicy,
+    /// Number of sleeping servers
+    sleeping: usize,
+    /// Number of idle servers in the background setup
+    starting: usize,
+    /// Number of background setups that are not scheduled yet
+    pending: usize,
+    /// Number of servers in the background setup that were taken by calls
+    claimed: usize,
+    /// Number of awake servers since the last statistics update
+    awake_servers: usize,
+    statistics: Option<EnergyStatisticsRaw>
+}
+
+impl Sleep {
+    /// Returns the reason if setup stream parameters are not valid or blocking policy keeps no server awake
+    pub fn new(description: &SleepDescription, v: usize) -> Result<Self, String> {
+        let awake = description.awake.min(v);
+        if description.policy == SleepPolicy::Block && awake == 0 {
+            return Err("Sleeping servers with blocking policy need at least one awake server".to_string());
+        }
+        Ok(Sleep {
+            setup: description.setup.get_stream().ok_or("Wrong setup stream parameters")?,
+            awake: awake,
+            policy: description.policy,
+            sleeping: v - awake,
+            starting: 0,
+            pending: 0,
+            claimed: 0,
+            awake_servers: awake,
+            statistics: None
+        })
+    }
+
+    pub fn get_setup_time<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.setup.sample(rng)
+    }
+
+    /// Number of idle servers that are awake and ready to serve when `v_free` servers are free
+    fn ready(&self, v_free: usize) -> usize {
+        v_free - self.sleeping - self.starting
+    }
+
+    /// Checks if the call can be admitted when `v_free` servers are free
+    pub fn fits(&self, v_free: usize, t: usize) -> bool {
+        match self.policy {
+            SleepPolicy::Wait => v_free >= t,
+            SleepPolicy::Block => self.ready(v_free) >= t
+        }
+    }
+
+    /// Accounts new call. Blocked call is blocked due to sleeping servers if enough servers are free.
+    pub fn call_offered(&mut self, blocked: bool, v_free: usize, t: usize) {
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_calls += 1;
+            if blocked && v_free >= t {
+                stat.no_of_blocked_sleeping += 1;
+            }
+        }
+    }
+
+    /// Call takes `t` of `v_free` servers. Returns the number of servers that need setup before its service.
+    /// Sleeping servers are woken up, when there are too few of them the call takes servers in the background setup.
+    /// The call waits for the setup time drawn again, it is the residual setup time for exponential setups.
+    pub fn call_add(&mut self, v_free: usize, t: usize) -> usize {
+        let missing = t.saturating_sub(self.ready(v_free));
+        let woken = missing.min(self.sleeping);
+        let claimed = missing - woken;
+        self.sleeping -= woken;
+        self.starting -= claimed;
+        self.claimed += claimed;
+        let reserve = self.ready(v_free - t) + self.starting;
+        let refill = self.awake.saturating_sub(reserve).min(self.sleeping);
+        self.sleeping -= refill;
+        self.starting += refill;
+        self.pending += refill;
+        if let Some(stat) = &mut self.statistics {
+            stat.no_of_setups += (woken + refill) as u64;
+        }
+        missing
+    }
+
+    /// Returns the number of background setups started since the last call and forgets them
+    pub fn take_pending_setups(&mut self) -> usize {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Server finished its background setup and is ready to serve, unless a call has taken it already
+    pub fn setup_done(&mut self) {
+        if self.claimed > 0 {
+            self.claimed -= 1;
+        } else {
+            self.starting -= 1;
+        }
+    }
+
+    /// Servers were released and `v_free` servers are free, idle servers above the reserve go to sleep
+    pub fn call_end(&mut self, v_free: usize) {
+        let idle = self.ready(v_free) + self.starting;
+        if idle > self.awake {
+            self.sleeping += (idle - self.awake).min(self.ready(v_free));
+        }
+    }
+
+    /// Accounts time with the number of awake servers from the previous update
+    pub fn time_update(&mut self, v: usize, time_delta: f64) {
+        if let Some(stat) = &mut self.statistics {
+            stat.update_time(self.awake_servers, time_delta);
+        }
+        self.awake_servers = v - self.sleeping;
+    }
+
+    pub fn statistics_init(&mut self, v: usize) {
+        self.statistics = Some(EnergyStatisticsRaw::new(v));
+    }
+
+    pub fn statistics_preview(&self) -> Option<EnergyStatistics> {
+        self.statistics.as_ref().map(|stat| stat.get_energy_statistics())
+    }
+}
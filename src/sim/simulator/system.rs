@@ -1,14 +1,75 @@
 use mongodb::bson::Uuid;
+use rand::Rng;
 
 use crate::sim::simulator::single_statistics::*;
+use crate::sim::simulator::queue::Queue;
+use crate::sim::simulator::breakdown::Breakdown;
+use crate::sim::simulator::compression::Compression;
+use crate::sim::simulator::windows::Windows;
+use crate::sim::simulator::sleep::Sleep;
+use crate::sim::simulator::servers::Servers;
+use crate::sim::simulator::sharing::Sharing;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Result of offering new call to the group
+pub enum CallAdmission
+{
+    Admitted,
+    /// Call waits in the queue, value is call identifier in the queue
+    Queued(u64),
+    /// Call woke up sleeping servers, its service starts after their setup
+    Setup,
+    Lost
+}
+
+/// Call taken to service after capacity was released
+pub struct StartedCall
+{
+    pub class_idx: usize,
+    pub t: usize,
+    /// Remaining service time of resumed preempted call, None for call taken from the queue
+    pub remaining_time: Option<f64>,
+    /// Arrival time of resumed preempted call, None for call taken from the queue
+    pub arrival: Option<f64>,
+    /// Time the call taken from the queue waited for service, 0 for resumed call
+    pub waiting_time: f64
+}
+
+/// Preempted call that waits to resume its service
+#[derive(Clone, Copy)]
+struct InterruptedCall
+{
+    class_idx: usize,
+    t: usize,
+    priority: u32,
+    remaining_time: f64,
+    arrival: f64
+}
+
 #[derive(Clone)]
 pub struct Group
 {
     pub v: usize,
     v_free: usize,
+    /// Optional waiting room in front of the group
+    pub queue: Option<Queue>,
+    /// Optional failures and repairs of servers
+    pub breakdown: Option<Breakdown>,
+    /// Elastic calls, present if any offered class compresses
+    pub compression: Option<Compression>,
+    /// Optional statistics in time windows
+    pub windows: Option<Windows>,
+    /// Optional sleeping servers with setup delays
+    pub sleep: Option<Sleep>,
+    /// Optional heterogeneous servers
+    pub servers: Option<Servers>,
+    /// Optional service rate depending on the macrostate
+    pub sharing: Option<Sharing>,
+    /// Preempted calls ordered by priority, they are resumed before calls from the queue
+    interrupted: Vec<InterruptedCall>,
+    /// Occupancy states that can be reached by offered classes
+    reachable: Vec<bool>,
     statistics: Option<StatisticsRunExperiment>
 }
 
@@ -17,52 +78,407 @@ impl Group {
         Self {
             v: capacity,
             v_free: capacity,
+            queue: None,
+            breakdown: None,
+            compression: None,
+            windows: None,
+            sleep: None,
+            servers: None,
+            sharing: None,
+            interrupted: Vec::new(),
+            reachable: vec![true; capacity + 1],
             statistics: None
         }
     }
 
-    pub fn call_add(&mut self, time_delta: f64) -> bool {
+    /// Marks occupancy states that are sums of calls' demands.
+    /// Every class is described by its demand `t` and optional limit of calls (finite sources).
+    pub fn reachable_states(v: usize, demands: &[(usize, Option<usize>)]) -> Vec<bool> {
+        let mut result = vec![false; v + 1];
+        result[0] = true;
+        for (t, max_calls) in demands {
+            if *t == 0 {
+                continue;
+            }
+            let max_calls = max_calls.unwrap_or(v / t).min(v / t);
+            for _ in 0..max_calls {
+                let mut changed = false;
+                for n in (*t..v + 1).rev() {
+                    if result[n - t] && !result[n] {
+                        result[n] = true;
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// Occupancy states that can't be reached because of constraints outside the group are not reachable
+    pub fn restrict_reachable(&mut self, reachable: &[bool]) {
+        for (state, reachable) in self.reachable.iter_mut().zip(reachable.iter()) {
+            *state = *state && *reachable;
+        }
+    }
+
+    pub fn get_v_free(&self) -> usize {
+        self.v_free
+    }
+
+    /// Number of BBUs occupied by calls, failed BBUs are not included.
+    /// With elastic calls it is the nominal number of BBUs demanded by calls in service.
+    pub fn occupancy(&self) -> usize {
+        if let Some(compression) = &self.compression {
+            return compression.occupancy();
+        }
+        let failed = self.breakdown.as_ref().map_or(0, |breakdown| breakdown.get_failed());
+        self.v - self.v_free - failed
+    }
+
+    /// Ratio of allocated to nominal bandwidth of elastic calls, 1 if calls are not compressed
+    pub fn compression_ratio(&self) -> f64 {
+        self.compression.as_ref().map_or(1f64, |compression| compression.get_ratio())
+    }
+
+    /// Service rate of every call in the current macrostate, 1 if the rate doesn't depend on the macrostate
+    /// or on the number of calls in service
+    pub fn service_rate(&self) -> f64 {
+        self.sharing.as_ref().map_or(1f64, |sharing| sharing.rate(self.occupancy()))
+    }
+
+    pub fn has_waiting_calls(&self) -> bool {
+        match &self.queue {
+            Some(queue) => !queue.is_empty(),
+            None => false
+        }
+    }
+
+    fn update_statistics(&mut self, event_type: EventType, class_idx: usize, old_state: usize, time_delta: f64) {
+        let new_state: usize = self.occupancy();
+        if let Some(stat) = &mut self.statistics {
+            stat.update(event_type, class_idx, old_state, new_state, time_delta);
+        }
+        if let Some(breakdown) = &mut self.breakdown {
+            breakdown.time_update(old_state, time_delta);
+        }
+        if let Some(compression) = &mut self.compression {
+            compression.time_update(old_state, time_delta);
+        }
+        if let Some(windows) = &mut self.windows {
+            windows.update(event_type, class_idx, old_state, time_delta);
+        }
+        if let Some(sleep) = &mut self.sleep {
+            sleep.time_update(self.v, time_delta);
+        }
+        if let Some(servers) = &mut self.servers {
+            servers.time_update(time_delta);
+        }
+        if let Some(sharing) = &mut self.sharing {
+            sharing.time_update(time_delta);
+        }
+    }
+
+    /// Tries to admit a call that demands `t` basic bandwidth units (BBU).
+    /// Call is blocked when less than `t` BBUs are free. Blocked call waits in the queue if
+    /// there is a free waiting place. When some calls are waiting, new call joins the queue.
+    pub fn call_add<R: Rng + ?Sized>(&mut self, class_idx: usize, t: usize, time_delta: f64, rng: &mut R) -> CallAdmission {
         let result;
-        let old_state: usize = (self.v - self.v_free) as usize;
-        let new_state: usize;
+        let old_state: usize = self.occupancy();
         let event_type: EventType;
 
-        if self.v_free > 0 {
+        let queue_empty = match &mut self.queue {
+            Some(queue) => {
+                queue.time_update(time_delta);
+                queue.is_empty()
+            }
+            None => true
+        };
+
+        let fits = match (&self.compression, &self.sleep) {
+            (Some(compression), _) => compression.can_admit(class_idx, t),
+            (None, Some(sleep)) => sleep.fits(self.v_free, t),
+            (None, None) => self.v_free >= t
+        };
+        let v_free = self.v_free;
+        if fits && queue_empty {
             event_type = EventType::NewCall;
-            new_state = old_state+1;
-            self.v_free -= 1;
-            result = true;
+            match &mut self.compression {
+                Some(compression) => {
+                    compression.call_add(class_idx, t);
+                    self.v_free = compression.get_v_free();
+                }
+                None => self.v_free -= t
+            }
+            if let Some(queue) = &mut self.queue {
+                queue.call_served_immediately();
+            }
+            if let Some(servers) = &mut self.servers {
+                servers.call_add(t, rng);
+            }
+            if let Some(sharing) = &mut self.sharing {
+                sharing.call_started();
+            }
+            let in_setup = self.sleep.as_mut().map_or(0, |sleep| sleep.call_add(v_free, t));
+            result = if in_setup > 0 { CallAdmission::Setup } else { CallAdmission::Admitted };
         }
         else {
-            event_type = EventType::LostCall;
-            new_state = old_state;
-            result = false;
+            match &mut self.queue {
+                Some(queue) if !queue.is_full() => {
+                    event_type = EventType::QueuedCall;
+                    result = CallAdmission::Queued(queue.push(class_idx, t));
+                }
+                Some(queue) => {
+                    event_type = EventType::LostCall;
+                    queue.call_rejected();
+                    result = CallAdmission::Lost;
+                }
+                None => {
+                    event_type = EventType::LostCall;
+                    result = CallAdmission::Lost;
+                }
+            }
         }
-        if let Some(stat) = &mut self.statistics {
-            stat.update(event_type, old_state, new_state, time_delta);
+        if let Some(breakdown) = &mut self.breakdown {
+            breakdown.call_offered(matches!(result, CallAdmission::Lost), old_state + t <= self.v);
+        }
+        if let Some(sleep) = &mut self.sleep {
+            sleep.call_offered(matches!(result, CallAdmission::Lost), v_free, t);
         }
+        self.update_statistics(event_type, class_idx, old_state, time_delta);
         result
     }
 
-    pub fn call_end(&mut self, time_delta: f64) {
+    /// Checks if `t` BBUs can be admitted at once, calls waiting in the queue go first
+    pub fn batch_fits(&self, class_idx: usize, t: usize) -> bool {
+        match (&self.compression, &self.sleep) {
+            (Some(compression), _) => compression.can_admit(class_idx, t),
+            (None, Some(sleep)) => sleep.fits(self.v_free, t),
+            (None, None) => self.v_free >= t && !self.has_waiting_calls()
+        }
+    }
+
+    /// Call is lost without trying to occupy BBUs, e.g. together with the rest of its batch
+    pub fn call_reject(&mut self, class_idx: usize, t: usize, time_delta: f64) {
+        let old_state: usize = self.occupancy();
+        if let Some(queue) = &mut self.queue {
+            queue.time_update(time_delta);
+        }
+        if let Some(breakdown) = &mut self.breakdown {
+            breakdown.call_offered(true, old_state + t <= self.v);
+        }
+        self.update_statistics(EventType::LostCall, class_idx, old_state, time_delta);
+    }
+
+    /// All calls of the batch were offered, `blocked` is true if any of them was lost
+    pub fn batch_offered(&mut self, class_idx: usize, blocked: bool) {
+        if let Some(stat) = &mut self.statistics {
+            stat.classes[class_idx].batch_offered(blocked);
+        }
+    }
+
+    /// Releases `t` BBUs and admits preempted and waiting calls that fit into the group.
+    /// Returns calls taken to service, their service should be started.
+    pub fn call_end(&mut self, class_idx: usize, t: usize, time_delta: f64) -> Vec<StartedCall> {
+
+        let old_state: usize = self.occupancy();
+        match &mut self.compression {
+            Some(compression) => {
+                compression.call_end(class_idx, t);
+                self.v_free = compression.get_v_free();
+            }
+            None => {
+                assert!(self.v_free + t <= self.v);
+                self.v_free += t;
+            }
+        }
+        if let Some(sleep) = &mut self.sleep {
+            sleep.call_end(self.v_free);
+        }
+        if let Some(sharing) = &mut self.sharing {
+            sharing.call_ended();
+        }
+        self.update_statistics(EventType::EndCall, class_idx, old_state, time_delta);
+        if let Some(queue) = &mut self.queue {
+            queue.time_update(time_delta);
+        }
+        self.admit_waiting()
+    }
+
+    /// Admits preempted and waiting calls that fit into free BBUs
+    fn admit_waiting(&mut self) -> Vec<StartedCall> {
+        let mut admitted = Vec::new();
+        while let Some(call) = self.interrupted.first() {
+            if call.t > self.v_free {
+                break;
+            }
+            let call = self.interrupted.remove(0);
+            self.v_free -= call.t;
+            if let Some(sharing) = &mut self.sharing {
+                sharing.call_started();
+            }
+            admitted.push(StartedCall { class_idx: call.class_idx, t: call.t, remaining_time: Some(call.remaining_time), arrival: Some(call.arrival), waiting_time: 0f64 });
+        }
+
+        if let Some(queue) = &mut self.queue {
+            while let Some(call) = queue.front() {
+                if call.t > self.v_free || !self.interrupted.is_empty() {
+                    break;
+                }
+                let waiting_time = queue.waiting_time(call);
+                let call = queue.pop().unwrap();
+                self.v_free -= call.t;
+                if let Some(sharing) = &mut self.sharing {
+                    sharing.call_started();
+                }
+                admitted.push(StartedCall { class_idx: call.class_idx, t: call.t, remaining_time: None, arrival: None, waiting_time: waiting_time });
+            }
+        }
+        admitted
+    }
+
+    /// One of working servers fails. Call of the busy server is moved to the free server if policy allows.
+    /// Returns identifier of the call that has to be interrupted with `call_interrupt`,
+    /// the failed server is accounted there.
+    pub fn server_fail<R: Rng + ?Sized>(&mut self, rng: &mut R, time_delta: f64) -> Option<u64> {
+        let state: usize = self.occupancy();
+        self.update_statistics(EventType::Other, 0, state, time_delta);
+        if let Some(queue) = &mut self.queue {
+            queue.time_update(time_delta);
+        }
+
+        let breakdown = self.breakdown.as_mut().expect("Group without breakdowns");
+        let interrupted = breakdown.server_fail(rng);
+        if interrupted.is_none() {
+            self.v_free -= 1;
+            breakdown.server_failed();
+        }
+        interrupted
+    }
 
-        assert!(self.v_free <= self.v);
+    /// Server of the call failed, the call is lost and its remaining `t - 1` BBUs are released.
+    /// Returns calls taken to service, their service should be started.
+    pub fn call_interrupt(&mut self, class_idx: usize, t: usize) -> Vec<StartedCall> {
+        let old_state: usize = self.occupancy();
+        self.v_free += t - 1;
+        if let Some(breakdown) = &mut self.breakdown {
+            breakdown.server_failed();
+            breakdown.call_interrupted();
+        }
+        if let Some(sharing) = &mut self.sharing {
+            sharing.call_ended();
+        }
+        self.update_statistics(EventType::InterruptedCall, class_idx, old_state, 0f64);
+        self.admit_waiting()
+    }
 
-        let event_type = EventType::EndCall;
-        let old_state: usize = (self.v - self.v_free) as usize;
+    /// Failed server was repaired. Returns calls taken to service, their service should be started.
+    pub fn server_repair(&mut self, time_delta: f64) -> Vec<StartedCall> {
+        let state: usize = self.occupancy();
+        self.update_statistics(EventType::Other, 0, state, time_delta);
+        if let Some(queue) = &mut self.queue {
+            queue.time_update(time_delta);
+        }
+        if let Some(breakdown) = &mut self.breakdown {
+            breakdown.server_repaired();
+        }
         self.v_free += 1;
-        let new_state: usize = (self.v - self.v_free) as usize;
+        self.admit_waiting()
+    }
 
-        if let Some(stat) = &mut self.statistics {
-            stat.update(event_type, old_state, new_state, time_delta);
+    /// Sleeping server finished its background setup
+    pub fn server_setup(&mut self, time_delta: f64) {
+        self.time_update(time_delta);
+        if let Some(sleep) = &mut self.sleep {
+            sleep.setup_done();
+        }
+    }
+
+    /// Releases `t` BBUs occupied by the call preempted by the call of higher priority.
+    /// Call with `remaining_time` waits to resume its service, otherwise it is lost.
+    /// `arrival` is the time of arrival of the call, it is kept till the call resumes.
+    pub fn call_preempt(&mut self, class_idx: usize, t: usize, priority: u32, remaining_time: Option<f64>, arrival: f64, time_delta: f64) {
+        assert!(self.v_free + t <= self.v);
+
+        if let Some(queue) = &mut self.queue {
+            queue.time_update(time_delta);
+        }
+        let old_state: usize = self.occupancy();
+        self.v_free += t;
+        if let Some(sharing) = &mut self.sharing {
+            sharing.call_ended();
+        }
+        self.update_statistics(EventType::PreemptedCall, class_idx, old_state, time_delta);
+
+        if let Some(remaining_time) = remaining_time {
+            let idx = self.interrupted.iter().position(|call| call.priority < priority).unwrap_or(self.interrupted.len());
+            self.interrupted.insert(idx, InterruptedCall { class_idx, t, priority, remaining_time, arrival });
         }
     }
 
-    pub fn statistics_init(&mut self) {
+    /// Patience of waiting call ran out. Returns true if the call has left the queue,
+    /// false if the call was already taken to service.
+    pub fn call_abandon(&mut self, class_idx: usize, call_id: u64, time_delta: f64) -> bool {
+        let old_state: usize = self.occupancy();
+        let abandoned = match &mut self.queue {
+            Some(queue) => {
+                queue.time_update(time_delta);
+                queue.abandon(call_id)
+            }
+            None => false
+        };
+        let event_type = if abandoned { EventType::AbandonedCall } else { EventType::Other };
+        self.update_statistics(event_type, class_idx, old_state, time_delta);
+        abandoned
+    }
+
+    /// Accounts time spent in the current state, when no call arrived or left the group
+    pub fn time_update(&mut self, time_delta: f64) {
+        let state: usize = self.occupancy();
+        self.update_statistics(EventType::Other, 0, state, time_delta);
+        if let Some(queue) = &mut self.queue {
+            queue.time_update(time_delta);
+        }
+    }
+
+    /// Every offered class is described by its demand `t` and optional limit of calls (finite sources)
+    pub fn statistics_init(&mut self, demands: &[(usize, Option<usize>)]) {
+        let no_of_classes = demands.len();
+        let max_occupancy = match &mut self.compression {
+            Some(compression) => {
+                compression.statistics_init();
+                self.reachable = compression.reachable_states(demands);
+                compression.max_occupancy()
+            }
+            None => {
+                self.reachable = Group::reachable_states(self.v, demands);
+                self.v
+            }
+        };
         match &mut self.statistics {
-            None => self.statistics = Option::Some(StatisticsRunExperiment::new(self.v)),
+            None => self.statistics = Option::Some(StatisticsRunExperiment::new(max_occupancy, no_of_classes)),
             Some(stat) => stat.clear()
         }
+        if let Some(queue) = &mut self.queue {
+            queue.statistics_init();
+        }
+        if let Some(breakdown) = &mut self.breakdown {
+            breakdown.statistics_init(self.v);
+        }
+        if let Some(windows) = &mut self.windows {
+            windows.statistics_init(max_occupancy + 1, no_of_classes);
+        }
+        if let Some(sleep) = &mut self.sleep {
+            sleep.statistics_init(self.v);
+        }
+        if let Some(servers) = &mut self.servers {
+            servers.statistics_init();
+        }
+        if let Some(sharing) = &mut self.sharing {
+            sharing.statistics_init(self.v);
+        }
     }
 
     pub fn statistics_preview(&self, no_of_events:u64, min_no_of_events_per_state: u32) -> StatisticsFinalized {
@@ -73,8 +489,19 @@ impl Group {
                 states : stat.states.iter().map(
                     |x| x.get_macrostate_statistics(total_time)
                 ).collect(),
+                classes : stat.classes.iter().map(
+                    |x| x.get_class_statistics(&stat.states)
+                ).collect(),
                 v: self.v,
                 no_of_events: no_of_events,
+                queue: self.queue.as_ref().and_then(|queue| queue.statistics_preview()),
+                orbit: None,
+                availability: self.breakdown.as_ref().and_then(|breakdown| breakdown.statistics_preview()),
+                compression: self.compression.as_ref().and_then(|compression| compression.statistics_preview()),
+                windows: self.windows.as_ref().and_then(|windows| windows.statistics_preview()),
+                energy: self.sleep.as_ref().and_then(|sleep| sleep.statistics_preview()),
+                servers: self.servers.as_ref().and_then(|servers| servers.statistics_preview()),
+                sharing: self.sharing.as_ref().and_then(|sharing| sharing.statistics_preview()),
                 metadata : StatisticsFinalizedMetadata {
                     min_no_of_events_per_state: min_no_of_events_per_state,
                     uuid: Uuid::new(),
@@ -85,10 +512,14 @@ impl Group {
         panic!("No raw statistics");
     }
 
+    /// Minimum number of events in occupancy states that can be reached
     pub fn min_state_occurance(&self) -> usize {
         match &self.statistics {
             Some(a) => {
-                match &a.states.iter().min_by(
+                match &a.states.iter().zip(self.reachable.iter())
+                    .filter(|(_, &reachable)| reachable)
+                    .map(|(itm, _)| itm)
+                    .min_by(
                     |&itm1, &itm2|
                         (itm1.no_out_new + itm1.no_out_end).cmp(&(itm2.no_out_new + itm2.no_out_end)))
                 {
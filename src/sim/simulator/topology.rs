@@ -0,0 +1,466 @@
+use mongodb::bson::Uuid;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::sim::model::class::StreamOfEvents;
+use crate::sim::model::topology::{AlternativeSelection, Route, TopologyDescription};
+use crate::sim::simulator::system::{CallAdmission, Group};
+use crate::sim::simulator::single_statistics::*;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Maximum number of joint states searched for reachable occupancy states of the single group
+const MAX_JOINT_STATES: usize = 200_000;
+
+/// Calls move between neighbouring cells after the dwell time
+#[derive(Clone)]
+struct Handoff
+{
+    neighbours: Vec<Vec<usize>>,
+    dwell: StreamOfEvents
+}
+
+/// Groups shared by traffic classes. Call is offered to the routes from the list of class
+/// alternatives, and it is lost when no alternative route has enough free BBUs in every group.
+#[derive(Clone)]
+pub struct Topology
+{
+    pub groups: Vec<Group>,
+    /// Routes available for calls of every class
+    alternatives: Vec<Vec<Route>>,
+    /// Groups used by all alternatives of every class
+    shared: Vec<Vec<usize>>,
+    selection: AlternativeSelection,
+    handoff: Option<Handoff>,
+    /// Number of BBUs of every group reserved for handoff calls
+    guard: Vec<usize>,
+    /// Simulation time, groups' statistics are updated only when their state changes
+    time: f64,
+    /// Time of the last statistics update of every group
+    last_update: Vec<f64>,
+    statistics: Option<Vec<RoutedClassStatisticsRaw>>,
+    /// Statistics of the total occupancy of all groups
+    total: Option<StatisticsRunExperiment>,
+    total_last_update: f64,
+    cells: Option<Vec<CellStatisticsRaw>>,
+    /// Demand of every class in every group, None if the class demands `t` BBUs in every group of its route
+    demands: Option<Vec<Vec<usize>>>,
+    /// Joint occupancy of groups since the last update of total statistics
+    joint_state: Vec<usize>,
+    joint: Option<JointStatisticsRaw>,
+    /// Occupancy states of every group reachable together with the other groups, None if every state is reachable
+    reachable: Option<Vec<Vec<bool>>>
+}
+
+impl Topology {
+    pub fn new(description: &TopologyDescription) -> Result<Self, String> {
+        if description.classes.is_empty() || description.classes.len() != description.alternatives.len() {
+            return Err("Every traffic class needs its list of alternative routes".to_string());
+        }
+        for alternatives in &description.alternatives {
+            if alternatives.is_empty() || alternatives.iter().any(|route|
+                route.is_empty() || route.iter().any(|idx| *idx >= description.groups.len())) {
+                return Err(format!("Wrong alternative routes {:?}, number of groups is {}", alternatives, description.groups.len()));
+            }
+        }
+
+        let mut guard = vec![0; description.groups.len()];
+        let mut handoff = None;
+        if let Some(description_handoff) = &description.handoff {
+            let no_of_groups = description.groups.len();
+            if description_handoff.neighbours.len() != no_of_groups || description_handoff.guard.len() != no_of_groups
+                || description_handoff.neighbours.iter().flatten().any(|idx| *idx >= no_of_groups)
+                || description.alternatives.iter().any(|alternatives| alternatives.len() != 1 || alternatives[0].len() != 1) {
+                return Err("Every cell needs its neighbours and guard BBUs, every class needs the only home cell".to_string());
+            }
+            guard = description_handoff.guard.clone();
+            handoff = Some(Handoff {
+                neighbours: description_handoff.neighbours.clone(),
+                dwell: description_handoff.dwell.get_stream().ok_or("Wrong dwell time stream parameters")?
+            });
+        }
+
+        if let Some(demands) = &description.demands {
+            if demands.len() != description.classes.len() || demands.iter().any(|class_demands| class_demands.len() != description.groups.len()) {
+                return Err("Every traffic class needs its demand of every group".to_string());
+            }
+        }
+
+        let shared = description.alternatives.iter()
+            .map(|alternatives| alternatives[0].iter()
+                .filter(|group_idx| alternatives.iter().all(|route| route.contains(group_idx)))
+                .cloned()
+                .collect())
+            .collect();
+
+        let mut topology = Topology {
+            groups: description.groups.iter().map(|v| Group::new(*v)).collect(),
+            alternatives: description.alternatives.clone(),
+            shared: shared,
+            selection: description.selection,
+            handoff: handoff,
+            guard: guard,
+            time: 0f64,
+            last_update: vec![0f64; description.groups.len()],
+            statistics: None,
+            total: None,
+            total_last_update: 0f64,
+            cells: None,
+            demands: description.demands.clone(),
+            joint_state: vec![0; description.groups.len()],
+            joint: None,
+            reachable: None
+        };
+        // Calls of the class occupy several groups at the same time, so some states of the single group can't be reached
+        if topology.handoff.is_none() && topology.alternatives.iter().flatten().any(|route| route.len() > 1) {
+            let demands: Vec<(usize, Option<usize>)> = description.classes.iter()
+                .map(|tr_class| (tr_class.get_t(), tr_class.get_sources()))
+                .collect();
+            topology.reachable = Some((0..topology.groups.len())
+                .map(|group_idx| topology.joint_reachable_states(group_idx, &demands))
+                .collect::<Result<Vec<Vec<bool>>, String>>()?);
+        }
+        Ok(topology)
+    }
+
+    /// Total number of BBUs in all groups
+    pub fn capacity(&self) -> usize {
+        self.groups.iter().map(|group| group.v).sum()
+    }
+
+    /// Number of BBUs occupied in all groups
+    pub fn occupancy(&self) -> usize {
+        self.groups.iter().map(|group| group.occupancy()).sum()
+    }
+
+    pub fn time_update(&mut self, time_delta: f64) {
+        self.time += time_delta;
+    }
+
+    /// Time since the last statistics update of the group
+    fn group_time_delta(&mut self, group_idx: usize) -> f64 {
+        let result = self.time - self.last_update[group_idx];
+        self.last_update[group_idx] = self.time;
+        result
+    }
+
+    fn update_total_statistics(&mut self, event_type: EventType, class_idx: usize, old_state: usize) {
+        let new_state = self.occupancy();
+        let time_delta = self.time - self.total_last_update;
+        self.total_last_update = self.time;
+        if let Some(stat) = &mut self.total {
+            stat.update(event_type, class_idx, old_state, new_state, time_delta);
+        }
+        if let Some(joint) = &mut self.joint {
+            joint.update_time(&self.joint_state, time_delta);
+            self.joint_state = self.groups.iter().map(|group| group.occupancy()).collect();
+        }
+    }
+
+    /// Number of BBUs that the call of the class with demand `t` occupies in the group
+    fn demand(&self, class_idx: usize, group_idx: usize, t: usize) -> usize {
+        match &self.demands {
+            Some(demands) => demands[class_idx][group_idx],
+            None => t
+        }
+    }
+
+    /// Number of free BBUs in the most occupied group of the class route.
+    /// Groups used by all alternatives of the class don't distinguish the routes, so they are skipped.
+    fn route_v_free(&self, class_idx: usize, route: &Route) -> usize {
+        route.iter()
+            .filter(|group_idx| !self.shared[class_idx].contains(group_idx))
+            .map(|group_idx| self.groups[*group_idx].get_v_free())
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// New call fits into the group if more than guard BBUs are left free
+    fn group_fits(&self, group_idx: usize, t: usize) -> bool {
+        self.groups[group_idx].get_v_free() >= t + self.guard[group_idx]
+    }
+
+    fn route_fits(&self, class_idx: usize, route: &Route, t: usize) -> bool {
+        route.iter().all(|group_idx| self.group_fits(*group_idx, self.demand(class_idx, *group_idx, t)))
+    }
+
+    /// Groups that can carry calls of the class, with handoffs these are cells reachable from the home cell
+    fn class_groups(&self, class_idx: usize) -> Vec<bool> {
+        let mut result = vec![false; self.groups.len()];
+        for group_idx in self.alternatives[class_idx].iter().flatten() {
+            result[*group_idx] = true;
+        }
+        if let Some(handoff) = &self.handoff {
+            let mut cells: Vec<usize> = self.alternatives[class_idx].iter().flatten().cloned().collect();
+            while let Some(cell) = cells.pop() {
+                for neighbour in &handoff.neighbours[cell] {
+                    if !result[*neighbour] {
+                        result[*neighbour] = true;
+                        cells.push(*neighbour);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Cell of the only route of the class
+    pub fn home_cell(&self, class_idx: usize) -> usize {
+        self.alternatives[class_idx][0][0]
+    }
+
+    pub fn has_handoff(&self) -> bool {
+        self.handoff.is_some()
+    }
+
+    /// Time that the call spends in the cell before its handoff, None without handoffs
+    pub fn get_dwell_time<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<f64> {
+        self.handoff.as_ref().map(|handoff| handoff.dwell.sample(rng))
+    }
+
+    /// Alternative chosen by the selection policy among the ones with enough free BBUs.
+    /// None if all alternatives should be tried in order.
+    fn preferred_alternative<R: Rng + ?Sized>(&self, class_idx: usize, t: usize, rng: &mut R) -> Option<usize> {
+        let fitting: Vec<usize> = self.alternatives[class_idx].iter().enumerate()
+            .filter(|(_, route)| self.route_fits(class_idx, route, t))
+            .map(|(alternative, _)| alternative)
+            .collect();
+        let v_free = |alternative: &usize| self.route_v_free(class_idx, &self.alternatives[class_idx][*alternative]);
+
+        match self.selection {
+            AlternativeSelection::Sequential => None,
+            AlternativeSelection::Random => fitting.choose(rng).copied(),
+            AlternativeSelection::LeastLoaded => fitting.iter()
+                .max_by(|a, b| v_free(a).cmp(&v_free(b)).then(b.cmp(a)))
+                .copied(),
+            AlternativeSelection::MostLoaded => fitting.iter()
+                .min_by(|a, b| v_free(a).cmp(&v_free(b)).then(a.cmp(b)))
+                .copied()
+        }
+    }
+
+    /// Offers the call to the alternative routes of its class.
+    /// Returns the index of alternative that admitted the call, None if the call was lost.
+    /// Blocked call is accounted in every tried group that has not enough free BBUs (guard BBUs are not free for new calls).
+    pub fn call_add<R: Rng + ?Sized>(&mut self, class_idx: usize, t: usize, rng: &mut R) -> Option<usize> {
+        let old_state = self.occupancy();
+        let order: Vec<usize> = match self.preferred_alternative(class_idx, t, rng) {
+            Some(alternative) => vec![alternative],
+            None => (0..self.alternatives[class_idx].len()).collect()
+        };
+
+        let mut result = None;
+        for alternative in order {
+            let route = self.alternatives[class_idx][alternative].clone();
+            let admitted = self.route_fits(class_idx, &route, t);
+            for group_idx in route {
+                let demand = self.demand(class_idx, group_idx, t);
+                if admitted {
+                    let time_delta = self.group_time_delta(group_idx);
+                    let admission = self.groups[group_idx].call_add(class_idx, demand, time_delta, rng);
+                    assert!(matches!(admission, CallAdmission::Admitted));
+                }
+                else if !self.group_fits(group_idx, demand) {
+                    let time_delta = self.group_time_delta(group_idx);
+                    self.groups[group_idx].call_reject(class_idx, demand, time_delta);
+                }
+            }
+            if admitted {
+                result = Some(alternative);
+                break;
+            }
+        }
+        let external = result.is_none() && self.shared[class_idx].iter()
+            .any(|group_idx| !self.group_fits(*group_idx, self.demand(class_idx, *group_idx, t)));
+        if let Some(stat) = &mut self.statistics {
+            stat[class_idx].call_offered(result, external, self.time);
+        }
+        let home_cell = self.home_cell(class_idx);
+        if let Some(cells) = &mut self.cells {
+            cells[home_cell].new_call(result.is_none());
+        }
+        let event_type = if result.is_some() { EventType::NewCall } else { EventType::LostCall };
+        self.update_total_statistics(event_type, class_idx, old_state);
+        result
+    }
+
+    /// Releases BBUs occupied by the call in every group of given alternative route
+    pub fn call_end(&mut self, class_idx: usize, t: usize, alternative: usize) {
+        let old_state = self.occupancy();
+        for group_idx in self.alternatives[class_idx][alternative].clone() {
+            let demand = self.demand(class_idx, group_idx, t);
+            let time_delta = self.group_time_delta(group_idx);
+            self.groups[group_idx].call_end(class_idx, demand, time_delta);
+        }
+        self.update_total_statistics(EventType::EndCall, class_idx, old_state);
+    }
+
+    /// Call leaves the cell and hands off to the random neighbouring cell. Guard BBUs are available for it.
+    /// Returns the new cell of the call, None if the call was dropped. Call stays if the cell has no neighbours.
+    pub fn handoff<R: Rng + ?Sized>(&mut self, class_idx: usize, t: usize, cell: usize, rng: &mut R) -> Option<usize> {
+        let handoff = self.handoff.as_ref().expect("Topology without handoffs");
+        let target = match handoff.neighbours[cell].choose(rng) {
+            Some(target) => *target,
+            None => return Some(cell)
+        };
+        let old_state = self.occupancy();
+        let time_delta = self.group_time_delta(cell);
+        self.groups[cell].call_end(class_idx, t, time_delta);
+
+        let time_delta = self.group_time_delta(target);
+        let dropped = self.groups[target].get_v_free() < t;
+        if dropped {
+            self.groups[target].call_reject(class_idx, t, time_delta);
+        }
+        else {
+            let admission = self.groups[target].call_add(class_idx, t, time_delta, rng);
+            assert!(matches!(admission, CallAdmission::Admitted));
+        }
+        if let Some(cells) = &mut self.cells {
+            cells[target].handoff(dropped);
+        }
+        // Dropped call leaves the system, handed off call doesn't change the total occupancy
+        let event_type = if dropped { EventType::EndCall } else { EventType::Other };
+        self.update_total_statistics(event_type, class_idx, old_state);
+        if dropped { None } else { Some(target) }
+    }
+
+    /// Releases BBUs occupied by the call in the cell it handed off to
+    pub fn call_end_in_cell(&mut self, class_idx: usize, t: usize, cell: usize) {
+        let old_state = self.occupancy();
+        let time_delta = self.group_time_delta(cell);
+        self.groups[cell].call_end(class_idx, t, time_delta);
+        self.update_total_statistics(EventType::EndCall, class_idx, old_state);
+    }
+
+    /// Occupancy states of the group that are reachable together with the other groups of routes.
+    /// Calls that don't use the group can only take BBUs of the other groups, so only calls routed
+    /// through the group are placed. Returns the reason if there are more than `MAX_JOINT_STATES` joint states
+    /// to search, since occupancy states that weren't found would be wrongly treated as unreachable.
+    fn joint_reachable_states(&self, group_idx: usize, demands: &[(usize, Option<usize>)]) -> Result<Vec<bool>, String> {
+        // Every call routed through the group takes BBUs in groups of its route
+        let mut moves: Vec<Vec<(usize, usize)>> = Vec::new();
+        for (class_idx, (t, _)) in demands.iter().enumerate() {
+            for route in self.alternatives[class_idx].iter().filter(|route| route.contains(&group_idx)) {
+                let step: Vec<(usize, usize)> = route.iter()
+                    .map(|idx| (*idx, self.demand(class_idx, *idx, *t)))
+                    .filter(|(_, demand)| *demand > 0)
+                    .collect();
+                if !step.is_empty() && !moves.contains(&step) {
+                    moves.push(step);
+                }
+            }
+        }
+
+        let mut result = vec![false; self.groups[group_idx].v + 1];
+        let mut visited = std::collections::BTreeSet::new();
+        let mut states = vec![vec![0; self.groups.len()]];
+        while let Some(state) = states.pop() {
+            if !visited.insert(state.clone()) {
+                continue;
+            }
+            result[state[group_idx]] = true;
+            if visited.len() > MAX_JOINT_STATES {
+                return Err(format!("Reachable states of group {} can't be found within {} joint states of all groups", group_idx, MAX_JOINT_STATES));
+            }
+            for step in &moves {
+                if step.iter().all(|(idx, demand)| state[*idx] + demand <= self.groups[*idx].v) {
+                    let mut next = state.clone();
+                    for (idx, demand) in step {
+                        next[*idx] += demand;
+                    }
+                    if !visited.contains(&next) {
+                        states.push(next);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Every offered class is described by its demand `t` and optional limit of calls (finite sources)
+    pub fn statistics_init(&mut self, demands: &[(usize, Option<usize>)]) {
+        let class_groups: Vec<Vec<bool>> = (0..self.alternatives.len()).map(|class_idx| self.class_groups(class_idx)).collect();
+        for group_idx in 0..self.groups.len() {
+            // Classes that don't use the group keep their index, but can't occupy it
+            let group_demands: Vec<(usize, Option<usize>)> = demands.iter().zip(class_groups.iter()).enumerate()
+                .map(|(class_idx, ((t, max_calls), groups))| {
+                    let demand = self.demand(class_idx, group_idx, *t);
+                    if groups[group_idx] { (demand, *max_calls) } else { (demand, Some(0)) }
+                })
+                .collect();
+            self.groups[group_idx].statistics_init(&group_demands);
+        }
+        if let Some(reachable) = &self.reachable {
+            for (group, reachable) in self.groups.iter_mut().zip(reachable.iter()) {
+                group.restrict_reachable(reachable);
+            }
+        }
+        self.last_update = vec![self.time; self.groups.len()];
+        self.total = Some(StatisticsRunExperiment::new(self.capacity(), demands.len()));
+        self.total_last_update = self.time;
+        self.statistics = Some(self.alternatives.iter()
+            .map(|alternatives| RoutedClassStatisticsRaw::new(alternatives.len()))
+            .collect());
+        if self.handoff.is_some() {
+            self.cells = Some(vec![CellStatisticsRaw::new(); self.groups.len()]);
+        }
+        if self.demands.is_some() {
+            self.joint_state = self.groups.iter().map(|group| group.occupancy()).collect();
+            self.joint = Some(JointStatisticsRaw::new());
+        }
+    }
+
+    /// Minimum number of events in reachable occupancy states of all groups. Groups that no class
+    /// occupies never leave the empty state, so they are skipped.
+    pub fn min_state_occurance(&self) -> usize {
+        let class_groups: Vec<Vec<bool>> = (0..self.alternatives.len()).map(|class_idx| self.class_groups(class_idx)).collect();
+        self.groups.iter().enumerate()
+            .filter(|(group_idx, _)| class_groups.iter().enumerate()
+                .any(|(class_idx, groups)| groups[*group_idx] && self.demand(class_idx, *group_idx, 1) > 0))
+            .map(|(_, group)| group.min_state_occurance())
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Accounts time since the last update of every group and processes the statistics
+    pub fn statistics_preview(&mut self, no_of_events: u64, min_no_of_events_per_state: u32) -> TopologyStatistics {
+        for group_idx in 0..self.groups.len() {
+            let time_delta = self.group_time_delta(group_idx);
+            self.groups[group_idx].time_update(time_delta);
+        }
+        let state = self.occupancy();
+        self.update_total_statistics(EventType::Other, 0, state);
+
+        let metadata = StatisticsFinalizedMetadata {
+            min_no_of_events_per_state: min_no_of_events_per_state,
+            uuid: Uuid::new(),
+            version: VERSION.to_string()
+        };
+        if let (Some(stat), Some(total)) = (&self.statistics, &self.total) {
+            return TopologyStatistics {
+                groups: self.groups.iter().map(|group| group.statistics_preview(no_of_events, min_no_of_events_per_state)).collect(),
+                total: StatisticsFinalized {
+                    states: total.states.iter().map(|x| x.get_macrostate_statistics(total.time_total)).collect(),
+                    classes: total.classes.iter().map(|x| x.get_class_statistics(&total.states)).collect(),
+                    v: self.capacity(),
+                    no_of_events: no_of_events,
+                    queue: None,
+                    orbit: None,
+                    availability: None,
+                    compression: None,
+                    windows: None,
+                    energy: None,
+                    servers: None,
+                    sharing: None,
+                    metadata: metadata.clone()
+                },
+                classes: stat.iter().map(|class| class.get_class_statistics()).collect(),
+                cells: self.cells.as_ref().map(|cells| cells.iter().map(|cell| cell.get_cell_statistics()).collect()),
+                joint: self.joint.as_ref().map(|joint| joint.get_joint_statistics()),
+                no_of_events: no_of_events,
+                metadata: metadata
+            };
+        }
+        panic!("No raw statistics");
+    }
+}
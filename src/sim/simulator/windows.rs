@@ -0,0 +1,116 @@
+use crate::sim::model::system::WindowsDescription;
+use crate::sim::simulator::single_statistics::{EventType, WindowStatisticsRaw, WindowStatistics};
+
+/// Maximum number of windows without the period. When the simulation gets longer,
+/// neighbouring windows are merged and the length of windows is doubled.
+const MAX_WINDOWS: usize = 1024;
+
+/// Statistics of the group in time windows. Windows follow each other from the start of the
+/// simulation or repeat after the period, then the same window of every period is accounted together.
+#[derive(Clone)]
+pub struct Windows
+{
+    length: f64,
+    period: Option<f64>,
+    /// Simulation time, it is counted also before the statistics are initialized
+    time: f64,
+    statistics: Option<Vec<WindowStatisticsRaw>>
+}
+
+impl Windows {
+    /// Returns the reason if the window is not shorter than the period
+    pub fn new(description: &WindowsDescription) -> Result<Self, String> {
+        if description.length <= 0f64 || description.period.map_or(false, |period| period < description.length) {
+            return Err(format!("Wrong windows: length {}, period {:?}", description.length, description.period));
+        }
+        Ok(Windows {
+            length: description.length,
+            period: description.period,
+            time: 0f64,
+            statistics: None
+        })
+    }
+
+    /// Index of window that contains given time and the end of this window
+    fn window(length: f64, period: Option<f64>, time: f64) -> (usize, f64) {
+        let (base, offset) = match period {
+            Some(period) => {
+                let base = (time / period).floor() * period;
+                (base, time - base)
+            }
+            None => (0f64, time)
+        };
+        let idx = (offset / length).floor() as usize;
+        let end = match period {
+            Some(period) => base + (((idx + 1) as f64) * length).min(period),
+            None => ((idx + 1) as f64) * length
+        };
+        (idx, end)
+    }
+
+    /// Accounts time spent in the state, split between the windows it spans,
+    /// and the event that ends this time in the window of the event
+    pub fn update(&mut self, event_type: EventType, class_idx: usize, state: usize, time_delta: f64) {
+        let end = self.time + time_delta;
+        self.merge_windows(end);
+        if let Some(stat) = &mut self.statistics {
+            let mut time = self.time;
+            while time < end {
+                let (idx, window_end) = Self::window(self.length, self.period, time);
+                // Rounding error can place the time at the end of its window, the rest stays there
+                let next = if window_end > time { window_end.min(end) } else { end };
+                Self::window_statistics(stat, idx).update_time(state, next - time);
+                time = next;
+            }
+        }
+        self.time = end;
+
+        let (idx, _) = Self::window(self.length, self.period, self.time);
+        if let Some(stat) = &mut self.statistics {
+            Self::window_statistics(stat, idx).update(event_type, class_idx);
+        }
+    }
+
+    /// Merges pairs of neighbouring windows till the window of `time` fits into `MAX_WINDOWS`.
+    /// Windows that repeat after the period are never merged, their number is bounded.
+    fn merge_windows(&mut self, time: f64) {
+        if self.period.is_some() {
+            return;
+        }
+        while Self::window(self.length, None, time).0 >= MAX_WINDOWS {
+            if let Some(stat) = &mut self.statistics {
+                let merged = stat.chunks(2)
+                    .map(|pair| {
+                        let mut window = pair[0].clone();
+                        if let Some(next) = pair.get(1) {
+                            window.merge(next);
+                        }
+                        window
+                    })
+                    .collect();
+                *stat = merged;
+            }
+            self.length *= 2f64;
+        }
+    }
+
+    fn window_statistics(stat: &mut Vec<WindowStatisticsRaw>, idx: usize) -> &mut WindowStatisticsRaw {
+        if stat.len() <= idx {
+            let (no_of_states, no_of_classes) = (stat[0].durations.len(), stat[0].no_of_calls.len());
+            stat.resize(idx + 1, WindowStatisticsRaw::new(no_of_states, no_of_classes));
+        }
+        &mut stat[idx]
+    }
+
+    pub fn statistics_init(&mut self, no_of_states: usize, no_of_classes: usize) {
+        self.statistics = Some(vec![WindowStatisticsRaw::new(no_of_states, no_of_classes)]);
+    }
+
+    /// Windows before the start of statistics are skipped
+    pub fn statistics_preview(&self) -> Option<Vec<WindowStatistics>> {
+        self.statistics.as_ref().map(|stat| stat.iter().enumerate()
+            .filter(|(_, window)| window.time_total > 0f64)
+            .map(|(idx, window)| window.get_window_statistics(idx as f64 * self.length, self.length))
+            .collect())
+    }
+}